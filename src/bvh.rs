@@ -0,0 +1,101 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+/// A bounding volume hierarchy node. Each node splits its objects in half along the longest axis
+/// of their combined bounding box, so a ray only has to visit the branches it actually overlaps.
+pub struct BvhNode {
+    left: Box<dyn Hittable>,
+    right: Option<Box<dyn Hittable>>,
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    pub fn new(mut objects: Vec<Box<dyn Hittable>>) -> Self {
+        // Build the bounding box of the span of source objects.
+        let bbox = objects.iter().fold(Aabb::EMPTY, |bbox, object| {
+            Aabb::enclosing(&bbox, &object.bounding_box())
+        });
+
+        match objects.len() {
+            0 => Self {
+                left: Box::new(HittableList::new()),
+                right: None,
+                bbox,
+            },
+            1 => Self {
+                left: objects.pop().unwrap(),
+                right: None,
+                bbox,
+            },
+            2 => {
+                let right = objects.pop();
+                Self {
+                    left: objects.pop().unwrap(),
+                    right,
+                    bbox,
+                }
+            }
+            _ => {
+                let axis = bbox.longest_axis();
+                objects.sort_by(|a, b| {
+                    let a_center = a.bounding_box().centroid()[axis];
+                    let b_center = b.bounding_box().centroid()[axis];
+                    a_center.total_cmp(&b_center)
+                });
+
+                let mid = objects.len() / 2;
+                let right_objects = objects.split_off(mid);
+                Self {
+                    left: Box::new(BvhNode::new(objects)),
+                    right: Some(Box::new(BvhNode::new(right_objects))),
+                    bbox,
+                }
+            }
+        }
+    }
+}
+
+impl From<HittableList> for BvhNode {
+    fn from(list: HittableList) -> Self {
+        BvhNode::new(list.into_objects())
+    }
+}
+
+impl Hittable for BvhNode {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(r, ray_t);
+        let closest_so_far = hit_left.as_ref().map_or(ray_t.max, |rec| rec.t);
+        let hit_right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(r, Interval::new(ray_t.min, closest_so_far)));
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        if !self.bbox.hit(r, ray_t) {
+            return 1.0;
+        }
+
+        let left = self.left.transmittance(r, ray_t);
+        if left <= 0.0 {
+            return 0.0;
+        }
+        let right = self
+            .right
+            .as_ref()
+            .map_or(1.0, |right| right.transmittance(r, ray_t));
+        left * right
+    }
+}
@@ -1,20 +1,33 @@
 use std::time::Instant;
 
-use nalgebra::Vector3;
+use nalgebra::{Vector3, Vector4};
 
 use crate::color::write_color;
-use crate::hittable::Hittable;
+use crate::environment::Environment;
+use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
+use crate::light::Light;
+use crate::material::ScatterResult;
+use crate::medium::Atmosphere;
 use crate::random_utils::{random_float, random_float_range};
 use crate::ray::Ray;
+use crate::spectrum::{SpectralConverter, Wavelengths};
 
 pub struct Camera {
     image_width: u16,
     image_height: u16,
     samples_per_pixel: u32,
     max_depth: u32,
+    background: Option<Vector3<f32>>,
+    environment: Option<Box<dyn Environment>>,
+    atmosphere: Option<Atmosphere>,
+    lights: Vec<Box<dyn Light>>,
+    /// Present when rendering spectrally rather than in RGB.
+    spectral: Option<SpectralConverter>,
 
     defocus_angle: f32,
+    shutter_open: f32,
+    shutter_close: f32,
     pixel_samples_scale: f32,
     center: Vector3<f32>,
     pixel00_loc: Vector3<f32>,
@@ -40,7 +53,16 @@ impl Camera {
                 let mut pixel_color = Vector3::new(0.0, 0.0, 0.0);
                 for _ in 0..self.samples_per_pixel {
                     let r = self.get_ray(i, j);
-                    pixel_color += Camera::ray_color(&r, self.max_depth, world);
+                    pixel_color += match &self.spectral {
+                        Some(converter) => {
+                            let wavelengths = Wavelengths::sample(random_float());
+                            let r = r.with_wavelengths(wavelengths);
+                            let radiance =
+                                self.spectral_ray_color(&r, self.max_depth, 0.0, world, converter);
+                            converter.to_rgb(&radiance, &wavelengths)
+                        }
+                        None => self.ray_color(&r, self.max_depth, 0.0, world),
+                    };
                 }
                 write_color(self.pixel_samples_scale * pixel_color);
             }
@@ -52,21 +74,214 @@ impl Camera {
         eprintln!("Max Depth: {}", self.max_depth);
         eprintln!("Render Time: {:.2?}\n", elapsed);
     }
-    fn ray_color(r: &Ray, depth: u32, world: &mut impl Hittable) -> Vector3<f32> {
+    /// The light arriving along the ray `r`, which was scattered in its direction with the
+    /// density `scatter_pdf`, or zero for camera rays and perfectly specular scattering.
+    fn ray_color(
+        &self,
+        r: &Ray,
+        depth: u32,
+        scatter_pdf: f32,
+        world: &mut impl Hittable,
+    ) -> Vector3<f32> {
         // If we've exceeded the ray bounce limit, no more light is gathered.
         if depth == 0 {
             return Vector3::new(0.0, 0.0, 0.0);
         }
 
-        if let Some(rec) = world.hit(r, Interval::new(0.001, f32::INFINITY)) {
-            if let Some(scatter) = rec.mat.scatter(r, &rec) {
-                return scatter.attenuation.component_mul(&Camera::ray_color(
-                    &scatter.scattered,
-                    depth - 1,
-                    world,
-                ));
+        // If the ray hits nothing, return the background color.
+        let Some(rec) = self.hit_world(r, world) else {
+            return self.environment_weight(r, scatter_pdf) * self.background_color(r);
+        };
+
+        let color_from_emission = rec.mat.emitted(rec.u, rec.v, &rec.p);
+        let color_from_lights = self.direct_light(r, &rec, world);
+        let Some(scatter) = rec.mat.scatter(r, &rec) else {
+            return color_from_emission + color_from_lights;
+        };
+
+        let attenuation = scatter.attenuation.component_mul(&rec.color);
+        let (color_from_exit, scatter_rec) = self.exit_light(r, &rec, &scatter, world);
+        let scatter_pdf = self.scatter_pdf(r, scatter_rec, &scatter.scattered);
+        let color_from_scatter = attenuation.component_mul(&self.ray_color(
+            &scatter.scattered,
+            depth - 1,
+            scatter_pdf,
+            world,
+        ));
+        color_from_emission
+            + color_from_lights
+            + attenuation.component_mul(&color_from_exit)
+            + color_from_scatter
+    }
+
+    /// Like [`Camera::ray_color`], for rays carrying wavelengths. Colors are upsampled to spectra
+    /// at each bounce, and the radiance at each wavelength is returned.
+    fn spectral_ray_color(
+        &self,
+        r: &Ray,
+        depth: u32,
+        scatter_pdf: f32,
+        world: &mut impl Hittable,
+        converter: &SpectralConverter,
+    ) -> Vector4<f32> {
+        let wavelengths = r.wavelengths().expect("spectral rays carry wavelengths");
+        if depth == 0 {
+            return Vector4::zeros();
+        }
+
+        let Some(rec) = self.hit_world(r, world) else {
+            let background = self.environment_weight(r, scatter_pdf) * self.background_color(r);
+            return converter.upsample(&background, &wavelengths);
+        };
+
+        let emission = rec.mat.emitted(rec.u, rec.v, &rec.p) + self.direct_light(r, &rec, world);
+        let radiance_from_emission = converter.upsample(&emission, &wavelengths);
+        let Some(scatter) = rec.mat.scatter(r, &rec) else {
+            return radiance_from_emission;
+        };
+
+        let attenuation = scatter.attenuation.component_mul(&rec.color);
+        let (color_from_exit, scatter_rec) = self.exit_light(r, &rec, &scatter, world);
+        let radiance_from_exit =
+            converter.upsample(&attenuation.component_mul(&color_from_exit), &wavelengths);
+        let scatter_pdf = self.scatter_pdf(r, scatter_rec, &scatter.scattered);
+        let mut scattered = scatter.scattered;
+        let scattered_wavelengths = scattered.wavelengths().unwrap_or(wavelengths);
+        scattered = scattered.with_wavelengths(scattered_wavelengths);
+
+        let mut radiance_from_scatter = converter
+            .upsample(&attenuation, &wavelengths)
+            .component_mul(&self.spectral_ray_color(
+                &scattered,
+                depth - 1,
+                scatter_pdf,
+                world,
+                converter,
+            ));
+        // Once the secondary wavelengths are dropped, the hero wavelength stands for all of them.
+        if scattered_wavelengths.is_hero_only() && !wavelengths.is_hero_only() {
+            radiance_from_scatter = Vector4::new(4.0 * radiance_from_scatter[0], 0.0, 0.0, 0.0);
+        }
+        radiance_from_emission + radiance_from_exit + radiance_from_scatter
+    }
+
+    /// The light reaching the camera along the ray `r` directly from the light sources and the
+    /// environment, after scattering at the hit `rec`.
+    fn direct_light(&self, r: &Ray, rec: &HitRecord, world: &mut impl Hittable) -> Vector3<f32> {
+        let mut color = Vector3::zeros();
+        for light in &self.lights {
+            let Some(sample) = light.sample(&rec.p) else {
+                continue;
+            };
+            let scattered = rec.mat.eval(r, rec, &sample.direction);
+            if scattered == Vector3::zeros() {
+                continue;
             }
-            return Vector3::new(0.0, 0.0, 0.0);
+            let shadow_ray = Ray::new(rec.p, sample.direction, r.time());
+            let transmittance = self.transmittance(&shadow_ray, sample.distance, world);
+            if transmittance > 0.0 {
+                color += transmittance * scattered.component_mul(&sample.radiance);
+            }
+        }
+
+        if let Some((direction, pdf)) = self.environment.as_ref().and_then(|env| env.sample()) {
+            let scattered = rec.mat.eval(r, rec, &direction);
+            if scattered != Vector3::zeros() && pdf > 0.0 {
+                let shadow_ray = Ray::new(rec.p, direction, r.time());
+                let transmittance = self.transmittance(&shadow_ray, f32::INFINITY, world);
+                if transmittance > 0.0 {
+                    // Scattered rays may find the same light, see `environment_weight`.
+                    let weight = power_heuristic(pdf, rec.mat.pdf(r, rec, &direction));
+                    let radiance = self.background_color(&shadow_ray);
+                    color += transmittance * weight / pdf * scattered.component_mul(&radiance);
+                }
+            }
+        }
+        color.component_mul(&rec.color)
+    }
+
+    /// The light scattered out at the exit of `scatter` when it leaves the surface elsewhere than
+    /// the hit `rec`, before the attenuation of the scattering, together with the hit where the
+    /// scattered ray starts.
+    fn exit_light<'a>(
+        &self,
+        r: &Ray,
+        rec: &'a HitRecord,
+        scatter: &'a ScatterResult,
+        world: &mut impl Hittable,
+    ) -> (Vector3<f32>, &'a HitRecord) {
+        match &scatter.exit {
+            Some(exit) => (self.direct_light(r, exit, world), exit),
+            None => (Vector3::zeros(), rec),
+        }
+    }
+
+    /// The density with which the material at `rec` scattered the ray `r` into `scattered`, which
+    /// is only needed when the environment is sampled directly.
+    fn scatter_pdf(&self, r: &Ray, rec: &HitRecord, scattered: &Ray) -> f32 {
+        match &self.environment {
+            Some(_) => rec.mat.pdf(r, rec, &scattered.direction().normalize()),
+            None => 0.0,
+        }
+    }
+
+    /// The weight of the environment seen by the ray `r`, scattered with the density
+    /// `scatter_pdf`, against the same light sampled directly by [`Camera::direct_light`].
+    fn environment_weight(&self, r: &Ray, scatter_pdf: f32) -> f32 {
+        match &self.environment {
+            Some(environment) if scatter_pdf > 0.0 => {
+                power_heuristic(scatter_pdf, environment.pdf(&r.direction().normalize()))
+            }
+            _ => 1.0,
+        }
+    }
+
+    /// The fraction of light travelling along the unit length ray `r` up to the distance
+    /// `distance` without being blocked, see [`Hittable::transmittance`]. Like other rays leaving
+    /// the scene, rays towards infinitely distant lights aren't attenuated by the atmosphere.
+    fn transmittance(&self, r: &Ray, distance: f32, world: &mut impl Hittable) -> f32 {
+        // Stop short of the light, which may lie on a surface.
+        let t_max = distance * (1.0 - 1e-4);
+        let transmittance = world.transmittance(r, Interval::new(0.001, t_max));
+        match &self.atmosphere {
+            Some(atmosphere) if distance.is_finite() => {
+                transmittance * atmosphere.transmittance(distance)
+            }
+            _ => transmittance,
+        }
+    }
+
+    /// Finds the surface hit by the ray, or the point where it scatters in the atmosphere before
+    /// reaching it. Rays go on through transparent parts of surfaces.
+    fn hit_world(&self, r: &Ray, world: &mut impl Hittable) -> Option<HitRecord> {
+        let mut t_min = 0.001;
+        let mut rec = loop {
+            let rec = world.hit(r, Interval::new(t_min, f32::INFINITY))?;
+            let opacity = rec.mat.opacity(rec.u, rec.v, &rec.p);
+            if opacity >= 1.0 || random_float() < opacity {
+                break rec;
+            }
+            // Step past the surface like past the origin of scattered rays.
+            t_min = rec.t + 0.001;
+        };
+
+        // The ray may scatter in the atmosphere before reaching the surface.
+        if let Some(atmosphere) = &self.atmosphere {
+            if let Some(medium_rec) = atmosphere.scatter_before(r, 0.0, rec.t) {
+                rec = medium_rec;
+            }
+        }
+        Some(rec)
+    }
+
+    /// The color seen by rays leaving the scene, from the environment, a fixed color or a blue
+    /// sky gradient.
+    fn background_color(&self, r: &Ray) -> Vector3<f32> {
+        if let Some(environment) = &self.environment {
+            return environment.radiance(&r.direction().normalize());
+        }
+        if let Some(background) = self.background {
+            return background;
         }
 
         let unit_direction = r.direction().normalize();
@@ -75,7 +290,7 @@ impl Camera {
     }
 
     /// Construct a camera ray originating from the defocus disk and directed at a randomly sampled
-    /// point around the pixel location i, j.
+    /// point around the pixel location i, j, at a random time while the shutter is open.
     fn get_ray(&self, i: u16, j: u16) -> Ray {
         let offset = Camera::sample_square();
         let pixel_sample = self.pixel00_loc
@@ -86,7 +301,8 @@ impl Camera {
         } else {
             self.defocus_disk_sample()
         };
-        Ray::new(ray_origin, pixel_sample - ray_origin)
+        let ray_time = random_float_range(self.shutter_open, self.shutter_close);
+        Ray::new(ray_origin, pixel_sample - ray_origin, ray_time)
     }
 
     fn random_in_unit_disk() -> Vector3<f32> {
@@ -117,12 +333,19 @@ pub struct CameraBuilder {
     image_width: u16,
     samples_per_pixel: u32,
     max_depth: u32,
+    background: Option<Vector3<f32>>,
+    environment: Option<Box<dyn Environment>>,
+    atmosphere: Option<Atmosphere>,
+    lights: Vec<Box<dyn Light>>,
+    spectral: bool,
     vfov: f32,
     lookfrom: Vector3<f32>,
     lookat: Vector3<f32>,
     vup: Vector3<f32>,
     defocus_angle: f32,
     focus_dist: f32,
+    shutter_open: f32,
+    shutter_close: f32,
 }
 
 impl CameraBuilder {
@@ -133,12 +356,19 @@ impl CameraBuilder {
             image_width: 400,
             samples_per_pixel: 10,
             max_depth: 10,
+            background: None,
+            environment: None,
+            atmosphere: None,
+            lights: Vec::new(),
+            spectral: false,
             vfov: 20.0,
             lookfrom: Vector3::new(13.0, 2.0, 3.0),
             lookat: Vector3::new(0.0, 0.0, 0.0),
             vup: Vector3::new(0.0, 1.0, 0.0),
             defocus_angle: 0.6,
             focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 1.0,
         }
     }
 
@@ -163,6 +393,44 @@ impl CameraBuilder {
         self
     }
 
+    /// Use a fixed background color instead of the default sky gradient, e.g. black for scenes
+    /// lit only by area lights.
+    pub fn background(mut self, background: Vector3<f32>) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    /// Surround the scene with light arriving from far away, such as a [`Sky`], instead of a
+    /// background color.
+    ///
+    /// [`Sky`]: crate::sky::Sky
+    pub fn environment(mut self, environment: Box<dyn Environment>) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Fill the scene with a homogeneous participating medium such as haze or fog.
+    pub fn atmosphere(mut self, atmosphere: Atmosphere) -> Self {
+        self.atmosphere = Some(atmosphere);
+        self
+    }
+
+    /// Add a point, spot or directional light to the scene. Unlike emissive surfaces, these
+    /// lights are never hit by rays, but light every scattering point directly, with a shadow
+    /// ray towards them.
+    pub fn light(mut self, light: Box<dyn Light>) -> Self {
+        self.lights.push(light);
+        self
+    }
+
+    /// Trace wavelengths of light rather than RGB colors, converting the result to RGB through
+    /// the CIE XYZ color matching functions. This is slower and noisier in color, but lets
+    /// materials depend on the wavelength, e.g. for dispersion.
+    pub fn spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+
     pub fn vfov(mut self, vfov: f32) -> Self {
         self.vfov = vfov;
         self
@@ -193,6 +461,14 @@ impl CameraBuilder {
         self
     }
 
+    /// The time interval over which rays are cast, blurring objects that move within it. Use the
+    /// same value for both ends for an instantaneous exposure.
+    pub fn shutter(mut self, shutter_open: f32, shutter_close: f32) -> Self {
+        self.shutter_open = shutter_open;
+        self.shutter_close = shutter_close;
+        self
+    }
+
     // Build method to create the Camera object
     pub fn build(self) -> Camera {
         let image_height = ((self.image_width as f32 / self.aspect_ratio) as u16).max(1);
@@ -233,7 +509,14 @@ impl CameraBuilder {
             image_height,
             samples_per_pixel: self.samples_per_pixel,
             max_depth: self.max_depth,
+            background: self.background,
+            environment: self.environment,
+            atmosphere: self.atmosphere,
+            lights: self.lights,
+            spectral: self.spectral.then(SpectralConverter::new),
             defocus_angle: self.defocus_angle,
+            shutter_open: self.shutter_open,
+            shutter_close: self.shutter_close,
             pixel_samples_scale,
             center,
             pixel00_loc,
@@ -244,3 +527,21 @@ impl CameraBuilder {
         }
     }
 }
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The multiple importance sampling weight of a sample taken with density `pdf`, against another
+/// strategy which would have taken it with density `other_pdf`, following Veach's power
+/// heuristic.
+fn power_heuristic(pdf: f32, other_pdf: f32) -> f32 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b > 0.0 {
+        a / (a + b)
+    } else {
+        0.0
+    }
+}
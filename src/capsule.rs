@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::cylinder::{angle_u, quadratic_roots, side_roots, LocalHit};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+
+/// All points within a given radius of the segment from `p0` to `p1`: a cylinder closed off with
+/// hemispheres at both ends.
+pub struct Capsule {
+    base: Vector3<f32>,
+    height: f32,
+    radius: f32,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Capsule {
+    pub fn new(p0: Vector3<f32>, p1: Vector3<f32>, radius: f32, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vector3::new(radius, radius, radius);
+        let bbox = Aabb::enclosing(
+            &Aabb::from_points(p0 - rvec, p0 + rvec),
+            &Aabb::from_points(p1 - rvec, p1 + rvec),
+        );
+        let axis = p1 - p0;
+        Self {
+            base: p0,
+            height: axis.magnitude(),
+            radius,
+            onb: Onb::new(&axis),
+            mat,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Capsule {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Solve in the capsule's local frame, where the segment runs along z from 0 to height.
+        let o = self.onb.to_basis(&(r.origin() - self.base));
+        let d = self.onb.to_basis(&r.direction());
+
+        let mut nearest = None;
+
+        // The v coordinate runs from the bottom to the top of the capsule.
+        let total_length = self.height + 2.0 * self.radius;
+        let v = |z: f32| (z + self.radius) / total_length;
+
+        let (roots, count) = side_roots(&o, &d, self.radius);
+        for &t in &roots[..count] {
+            let p = o + t * d;
+            if (0.0..=self.height).contains(&p.z) {
+                let hit = LocalHit {
+                    t,
+                    normal: Vector3::new(p.x, p.y, 0.0) / self.radius,
+                    u: angle_u(&p),
+                    v: v(p.z),
+                };
+                LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+            }
+        }
+
+        // Each hemisphere only covers the part of its sphere beyond the end of the segment.
+        for (center_z, beyond) in [(0.0, -1.0), (self.height, 1.0)] {
+            let oc = Vector3::new(0.0, 0.0, center_z) - o;
+            let a = d.magnitude_squared();
+            let h = d.dot(&oc);
+            let c = oc.magnitude_squared() - self.radius * self.radius;
+            let (roots, count) = quadratic_roots(a, h, c);
+            for &t in &roots[..count] {
+                let p = o + t * d;
+                if (p.z - center_z) * beyond >= 0.0 {
+                    let hit = LocalHit {
+                        t,
+                        normal: (p - Vector3::new(0.0, 0.0, center_z)) / self.radius,
+                        u: angle_u(&p),
+                        v: v(p.z),
+                    };
+                    LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+                }
+            }
+        }
+
+        nearest.map(|hit| hit.into_record(r, &self.onb, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
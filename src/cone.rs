@@ -0,0 +1,93 @@
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::cylinder::{angle_u, cap_hit, disk_extent, quadratic_roots, LocalHit};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+
+/// A cone narrowing from a circular base of a given radius to its apex, optionally closed off
+/// with a flat cap at the base.
+pub struct Cone {
+    base: Vector3<f32>,
+    height: f32,
+    radius: f32,
+    capped: bool,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Cone {
+    pub fn new(
+        base: Vector3<f32>,
+        apex: Vector3<f32>,
+        radius: f32,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let axis = apex - base;
+        let onb = Onb::new(&axis);
+        let extent = disk_extent(&onb.w(), radius);
+        let bbox = Aabb::enclosing(
+            &Aabb::from_points(base - extent, base + extent),
+            &Aabb::from_points(apex, apex),
+        );
+        Self {
+            base,
+            height: axis.magnitude(),
+            radius,
+            capped,
+            onb,
+            mat,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Solve in the cone's local frame, with the base at z = 0 and the apex at z = height.
+        let o = self.onb.to_basis(&(r.origin() - self.base));
+        let d = self.onb.to_basis(&r.direction());
+
+        let mut nearest = None;
+
+        // The radius shrinks linearly towards the apex: x² + y² = (k (height - z))².
+        let k2 = (self.radius / self.height).powi(2);
+        let q = self.height - o.z;
+        let a = d.x * d.x + d.y * d.y - k2 * d.z * d.z;
+        let h = -(o.x * d.x + o.y * d.y + k2 * q * d.z);
+        let c = o.x * o.x + o.y * o.y - k2 * q * q;
+
+        let (roots, count) = quadratic_roots(a, h, c);
+        for &t in &roots[..count] {
+            let p = o + t * d;
+            if (0.0..=self.height).contains(&p.z) {
+                let hit = LocalHit {
+                    t,
+                    normal: Vector3::new(p.x, p.y, k2 * (self.height - p.z)).normalize(),
+                    u: angle_u(&p),
+                    v: p.z / self.height,
+                };
+                LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+            }
+        }
+
+        if self.capped {
+            if let Some(hit) = cap_hit(&o, &d, 0.0, -1.0, self.radius) {
+                LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+            }
+        }
+
+        nearest.map(|hit| hit.into_record(r, &self.onb, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
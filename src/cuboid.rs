@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use nalgebra::{Rotation3, Vector3};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable, HittableList};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::quad::Quad;
+use crate::ray::Ray;
+
+/// A box made of six quads, each with its own [0, 1] texture coordinates.
+pub struct Cuboid {
+    sides: HittableList,
+}
+
+impl Cuboid {
+    /// Creates the axis-aligned box with the two opposite corners `a` and `b`.
+    pub fn new(a: Vector3<f32>, b: Vector3<f32>, mat: Arc<dyn Material>) -> Self {
+        Self::with_rotation(a, b, Rotation3::identity(), mat)
+    }
+
+    /// Creates the box with the two opposite corners `a` and `b`, rotated around its center.
+    pub fn with_rotation(
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        rotation: Rotation3<f32>,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let min = a.inf(&b);
+        let max = a.sup(&b);
+        let center = 0.5 * (min + max);
+
+        let corner = |x: f32, y: f32, z: f32| center + rotation * (Vector3::new(x, y, z) - center);
+        let dx = rotation * Vector3::new(max.x - min.x, 0.0, 0.0);
+        let dy = rotation * Vector3::new(0.0, max.y - min.y, 0.0);
+        let dz = rotation * Vector3::new(0.0, 0.0, max.z - min.z);
+
+        // Each side is spanned so that its normal points out of the box.
+        let mut sides = HittableList::new();
+        let mut side = |q: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>| {
+            sides.add(Box::new(Quad::new(q, u, v, Arc::clone(&mat))));
+        };
+        side(corner(min.x, min.y, max.z), dx, dy); // front
+        side(corner(max.x, min.y, max.z), -dz, dy); // right
+        side(corner(max.x, min.y, min.z), -dx, dy); // back
+        side(corner(min.x, min.y, min.z), dz, dy); // left
+        side(corner(min.x, max.y, max.z), dx, -dz); // top
+        side(corner(min.x, min.y, min.z), dx, dz); // bottom
+
+        Self { sides }
+    }
+}
+
+impl Hittable for Cuboid {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.sides.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sides.bounding_box()
+    }
+}
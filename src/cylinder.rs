@@ -0,0 +1,186 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+
+/// A cylinder of a given radius around the segment from `p0` to `p1`, optionally closed off with
+/// flat caps at both ends.
+pub struct Cylinder {
+    base: Vector3<f32>,
+    height: f32,
+    radius: f32,
+    capped: bool,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Cylinder {
+    pub fn new(
+        p0: Vector3<f32>,
+        p1: Vector3<f32>,
+        radius: f32,
+        capped: bool,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let axis = p1 - p0;
+        let onb = Onb::new(&axis);
+        let extent = disk_extent(&onb.w(), radius);
+        let bbox = Aabb::enclosing(
+            &Aabb::from_points(p0 - extent, p0 + extent),
+            &Aabb::from_points(p1 - extent, p1 + extent),
+        );
+        Self {
+            base: p0,
+            height: axis.magnitude(),
+            radius,
+            capped,
+            onb,
+            mat,
+            bbox,
+        }
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // Solve in the cylinder's local frame, where its axis runs along z from 0 to height.
+        let o = self.onb.to_basis(&(r.origin() - self.base));
+        let d = self.onb.to_basis(&r.direction());
+
+        let mut nearest = None;
+
+        let (roots, count) = side_roots(&o, &d, self.radius);
+        for &t in &roots[..count] {
+            let p = o + t * d;
+            if (0.0..=self.height).contains(&p.z) {
+                let hit = LocalHit {
+                    t,
+                    normal: Vector3::new(p.x, p.y, 0.0) / self.radius,
+                    u: angle_u(&p),
+                    v: p.z / self.height,
+                };
+                LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+            }
+        }
+
+        if self.capped {
+            for (z, normal_z) in [(0.0, -1.0), (self.height, 1.0)] {
+                if let Some(hit) = cap_hit(&o, &d, z, normal_z, self.radius) {
+                    LocalHit::keep_nearest(&mut nearest, hit, ray_t);
+                }
+            }
+        }
+
+        nearest.map(|hit| hit.into_record(r, &self.onb, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// An intersection found in the local frame of a shape.
+pub(crate) struct LocalHit {
+    pub t: f32,
+    pub normal: Vector3<f32>,
+    pub u: f32,
+    pub v: f32,
+}
+
+impl LocalHit {
+    /// Replaces the nearest of the candidate hits found so far with `hit`, if it is closer and
+    /// within the ray interval.
+    pub fn keep_nearest(nearest: &mut Option<LocalHit>, hit: LocalHit, ray_t: Interval) {
+        if ray_t.surrounds(hit.t) && nearest.as_ref().is_none_or(|nearest| hit.t < nearest.t) {
+            *nearest = Some(hit);
+        }
+    }
+
+    /// Converts the hit to a hit record, transforming the local outward normal to world space.
+    pub fn into_record(self, r: &Ray, onb: &Onb, mat: &Arc<dyn Material>) -> HitRecord {
+        let mut rec = HitRecord::new(r.at(self.t), self.t, Arc::clone(mat));
+        rec.u = self.u;
+        rec.v = self.v;
+        rec.set_face_normal(r, onb.transform(&self.normal).normalize());
+        // Around the axis, u follows the angle, while on flat caps it follows x.
+        let around = Vector3::new(-self.normal.y, self.normal.x, 0.0);
+        rec.tangent = if around.magnitude_squared() > 1e-12 {
+            onb.transform(&around)
+        } else {
+            onb.u()
+        };
+        rec
+    }
+}
+
+/// The ray parameters where the ray hits the infinite cylinder of the given radius around the z
+/// axis, as returned by [`quadratic_roots`].
+pub(crate) fn side_roots(o: &Vector3<f32>, d: &Vector3<f32>, radius: f32) -> ([f32; 2], usize) {
+    let a = d.x * d.x + d.y * d.y;
+    let h = -(o.x * d.x + o.y * d.y);
+    let c = o.x * o.x + o.y * o.y - radius * radius;
+    quadratic_roots(a, h, c)
+}
+
+/// Solves `a t² - 2h t + c = 0`, returning the real roots in ascending order in the first
+/// elements of the array, followed by their number.
+pub(crate) fn quadratic_roots(a: f32, h: f32, c: f32) -> ([f32; 2], usize) {
+    if a.abs() < 1e-12 {
+        // The equation degenerates to a linear one.
+        return if h.abs() < 1e-12 {
+            ([0.0; 2], 0)
+        } else {
+            ([c / (2.0 * h), 0.0], 1)
+        };
+    }
+
+    let discriminant = h * h - a * c;
+    if discriminant < 0.0 {
+        return ([0.0; 2], 0);
+    }
+    let sqrtd = discriminant.sqrt();
+    let (t0, t1) = ((h - sqrtd) / a, (h + sqrtd) / a);
+    ([t0.min(t1), t0.max(t1)], 2)
+}
+
+/// Intersects a flat circular cap at height `z` in the local frame.
+pub(crate) fn cap_hit(
+    o: &Vector3<f32>,
+    d: &Vector3<f32>,
+    z: f32,
+    normal_z: f32,
+    radius: f32,
+) -> Option<LocalHit> {
+    if d.z.abs() < 1e-12 {
+        return None;
+    }
+    let t = (z - o.z) / d.z;
+    let p = o + t * d;
+    if p.x * p.x + p.y * p.y > radius * radius {
+        return None;
+    }
+    Some(LocalHit {
+        t,
+        normal: Vector3::new(0.0, 0.0, normal_z),
+        u: 0.5 * (p.x / radius + 1.0),
+        v: 0.5 * (p.y / radius + 1.0),
+    })
+}
+
+/// The texture coordinate running around the local z axis.
+pub(crate) fn angle_u(p: &Vector3<f32>) -> f32 {
+    (p.y.atan2(p.x) + PI) / (2.0 * PI)
+}
+
+/// The extent along each world axis of a disk with the given radius facing along `axis`.
+pub(crate) fn disk_extent(axis: &Vector3<f32>, radius: f32) -> Vector3<f32> {
+    axis.map(|n| radius * (1.0 - n * n).max(0.0).sqrt())
+}
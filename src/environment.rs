@@ -0,0 +1,264 @@
+//! The light arriving from infinitely far away, seen by rays leaving the scene.
+
+use std::f32::consts::PI;
+use std::path::Path;
+
+use image::ImageResult;
+use nalgebra::Vector3;
+
+use crate::random_utils::random_float;
+use crate::texture::ImageTexture;
+
+/// Surrounds the scene, giving the radiance of rays that leave it, set with
+/// [`CameraBuilder::environment`](crate::camera::CameraBuilder::environment).
+///
+/// Environments that can be sampled light every scattering point directly, like light sources,
+/// in addition to being found by scattered rays. Both ways are combined with multiple importance
+/// sampling, which keeps the noise low for small bright features such as the sun in a
+/// photograph as well as for glossy reflections of the whole environment.
+pub trait Environment: Send + Sync {
+    /// The radiance arriving from the unit direction `direction`, i.e. seen by a ray travelling
+    /// opposite to it.
+    fn radiance(&self, direction: &Vector3<f32>) -> Vector3<f32>;
+
+    /// Samples a unit direction towards the environment, favoring the brightest ones, and
+    /// returns it with its density over solid angle. Environments which aren't sampled return
+    /// `None`, like the default, and are only found by scattered rays.
+    fn sample(&self) -> Option<(Vector3<f32>, f32)> {
+        None
+    }
+
+    /// The density over solid angle of [`Environment::sample`] returning `direction`.
+    fn pdf(&self, _direction: &Vector3<f32>) -> f32 {
+        0.0
+    }
+}
+
+/// A panoramic image of the surroundings in the equirectangular (latitude-longitude) layout of
+/// most HDR photographs, with the zenith at the top and the -z direction in the middle.
+///
+/// The environment is sampled following the luminance of its pixels.
+pub struct EnvironmentMap {
+    image: ImageTexture,
+    distribution: Option<LatLongDistribution>,
+    /// Rotation about the y axis, in radians.
+    rotation: f32,
+    intensity: f32,
+}
+
+impl EnvironmentMap {
+    pub fn new(image: ImageTexture) -> Self {
+        let distribution =
+            LatLongDistribution::new(image.width() as usize, image.height() as usize, |i, j| {
+                luminance(&image.pixel(i as u32, j as u32))
+            });
+        Self {
+            image,
+            distribution,
+            rotation: 0.0,
+            intensity: 1.0,
+        }
+    }
+
+    /// Load an environment map from disk, usually a floating point image such as a `.hdr` file.
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Self::new(ImageTexture::open(path)?))
+    }
+
+    /// Turns the environment about the vertical axis by `degrees`, clockwise seen from above.
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees.to_radians();
+        self
+    }
+
+    /// Scales the radiance of the whole environment.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+}
+
+impl Environment for EnvironmentMap {
+    fn radiance(&self, direction: &Vector3<f32>) -> Vector3<f32> {
+        let (width, height) = (self.image.width() as usize, self.image.height() as usize);
+        if width == 0 || height == 0 {
+            return Vector3::zeros();
+        }
+        let (u, v) = lat_long_coords(&rotate_y(direction, -self.rotation));
+        let (i, j) = cell_index(u, v, width, height);
+        self.intensity * self.image.pixel(i as u32, j as u32)
+    }
+
+    fn sample(&self) -> Option<(Vector3<f32>, f32)> {
+        let (direction, pdf) = self.distribution.as_ref()?.sample()?;
+        Some((rotate_y(&direction, self.rotation), pdf))
+    }
+
+    fn pdf(&self, direction: &Vector3<f32>) -> f32 {
+        self.distribution
+            .as_ref()
+            .map_or(0.0, |d| d.pdf(&rotate_y(direction, -self.rotation)))
+    }
+}
+
+/// Samples directions over the sphere in proportion to a function tabulated over the cells of a
+/// latitude-longitude grid, laid out like [`EnvironmentMap`] images.
+pub(crate) struct LatLongDistribution {
+    distribution: Distribution2D,
+}
+
+impl LatLongDistribution {
+    /// Tabulates `value(i, j)` over `width` columns and `height` rows, or returns `None` if it
+    /// is zero everywhere.
+    pub(crate) fn new(
+        width: usize,
+        height: usize,
+        value: impl Fn(usize, usize) -> f32,
+    ) -> Option<Self> {
+        // Rows near the poles cover smaller solid angles.
+        let weights = (0..height)
+            .flat_map(|j| {
+                let sin_theta = ((j as f32 + 0.5) / height as f32 * PI).sin();
+                let value = &value;
+                (0..width).map(move |i| value(i, j) * sin_theta)
+            })
+            .collect();
+        Distribution2D::new(weights, width, height).map(|distribution| Self { distribution })
+    }
+
+    /// Samples a unit direction, returned with its density over solid angle.
+    pub(crate) fn sample(&self) -> Option<(Vector3<f32>, f32)> {
+        let ((u, v), pdf_uv) = self.distribution.sample(random_float(), random_float());
+
+        // Map the density over the grid to the sphere, whose area element is
+        // 2π² sin θ du dv.
+        let theta = v * PI;
+        let sin_theta = theta.sin();
+        if sin_theta <= 0.0 {
+            return None;
+        }
+        let phi = (u - 0.5) * 2.0 * PI;
+        let direction = Vector3::new(sin_theta * phi.sin(), theta.cos(), -sin_theta * phi.cos());
+        Some((direction, pdf_uv / (2.0 * PI * PI * sin_theta)))
+    }
+
+    /// The density over solid angle of [`LatLongDistribution::sample`] returning `direction`.
+    pub(crate) fn pdf(&self, direction: &Vector3<f32>) -> f32 {
+        let sin_theta = (1.0 - direction.y * direction.y).max(0.0).sqrt();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+        let (u, v) = lat_long_coords(direction);
+        let d = &self.distribution;
+        let (i, j) = cell_index(u, v, d.width, d.height);
+        d.pdf(i, j) / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+/// The coordinates in [0, 1] of a unit direction in the latitude-longitude layout, with v
+/// increasing downwards.
+fn lat_long_coords(direction: &Vector3<f32>) -> (f32, f32) {
+    let phi = direction.x.atan2(-direction.z);
+    let theta = direction.y.clamp(-1.0, 1.0).acos();
+    ((0.5 + phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+}
+
+/// The cell of a `width` by `height` grid holding the coordinates `u`, `v`.
+fn cell_index(u: f32, v: f32, width: usize, height: usize) -> (usize, usize) {
+    let i = ((u * width as f32) as usize).min(width - 1);
+    let j = ((v * height as f32) as usize).min(height - 1);
+    (i, j)
+}
+
+/// Rotates a direction about the y axis by `angle` radians, clockwise seen from above.
+fn rotate_y(direction: &Vector3<f32>, angle: f32) -> Vector3<f32> {
+    let (sin, cos) = angle.sin_cos();
+    Vector3::new(
+        direction.x * cos - direction.z * sin,
+        direction.y,
+        direction.z * cos + direction.x * sin,
+    )
+}
+
+/// A piecewise constant distribution over the unit square, sampled by picking a row from the
+/// marginal distribution of the rows, then a column from the conditional distribution within it.
+struct Distribution2D {
+    width: usize,
+    height: usize,
+    /// Unnormalized weights of the cells, stored row by row.
+    weights: Vec<f32>,
+    /// Cumulative weights within each row, stored row by row.
+    row_cdfs: Vec<f32>,
+    /// Cumulative total weights of the rows.
+    marginal_cdf: Vec<f32>,
+}
+
+impl Distribution2D {
+    /// Returns `None` if all weights are zero, which leaves nothing to sample.
+    fn new(weights: Vec<f32>, width: usize, height: usize) -> Option<Self> {
+        if width == 0 || height == 0 {
+            return None;
+        }
+        let mut row_cdfs = Vec::with_capacity(weights.len());
+        let mut marginal_cdf = Vec::with_capacity(height);
+        let mut total = 0.0;
+        for row in weights.chunks(width) {
+            let mut row_total = 0.0;
+            for &weight in row {
+                row_total += weight.max(0.0);
+                row_cdfs.push(row_total);
+            }
+            total += row_total;
+            marginal_cdf.push(total);
+        }
+        (total > 0.0 && total.is_finite()).then_some(Self {
+            width,
+            height,
+            weights,
+            row_cdfs,
+            marginal_cdf,
+        })
+    }
+
+    /// Samples a point of the unit square from the uniform random numbers `u1` and `u2`, and
+    /// returns it with its density.
+    fn sample(&self, u1: f32, u2: f32) -> ((f32, f32), f32) {
+        let (j, offset_v) = sample_cdf(&self.marginal_cdf, u1);
+        let row_cdf = &self.row_cdfs[j * self.width..(j + 1) * self.width];
+        let (i, offset_u) = sample_cdf(row_cdf, u2);
+        let point = (
+            (i as f32 + offset_u) / self.width as f32,
+            (j as f32 + offset_v) / self.height as f32,
+        );
+        (point, self.pdf(i, j))
+    }
+
+    /// The density of the points in the cell at column `i` and row `j`.
+    fn pdf(&self, i: usize, j: usize) -> f32 {
+        let total = self.marginal_cdf[self.height - 1];
+        let weight = self.weights[j * self.width + i].max(0.0);
+        weight / total * (self.width * self.height) as f32
+    }
+}
+
+/// Picks an interval from a cumulative distribution with the uniform random number `u`, and
+/// returns its index with the relative position of `u` within it.
+fn sample_cdf(cdf: &[f32], u: f32) -> (usize, f32) {
+    let total = cdf[cdf.len() - 1];
+    let target = u * total;
+    // The first interval ending after the target, skipping empty ones.
+    let index = cdf.partition_point(|&c| c <= target).min(cdf.len() - 1);
+    let start = if index == 0 { 0.0 } else { cdf[index - 1] };
+    let width = cdf[index] - start;
+    let offset = if width > 0.0 {
+        ((target - start) / width).clamp(0.0, 1.0 - f32::EPSILON)
+    } else {
+        0.5
+    };
+    (index, offset)
+}
+
+/// The luminance of a linear sRGB color.
+pub(crate) fn luminance(color: &Vector3<f32>) -> f32 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
@@ -0,0 +1,808 @@
+//! Loader for glTF 2.0 scenes, in JSON (`.gltf`) or binary (`.glb`) form.
+//!
+//! Buffers and images may be embedded as data URIs, stored in the binary chunk of a `.glb` file or
+//! be separate files next to the scene. Remote URIs are rejected, everything is read from local
+//! disk. Meshes are transformed into world space by their node hierarchy, and perspective cameras
+//! are returned as [`CameraBuilder`]s. Sparse accessors, orthographic cameras and materials whose
+//! textures use different texture coordinate sets are not supported.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::{Matrix3, Matrix4, Quaternion, UnitQuaternion, Vector2, Vector3, Vector4};
+use serde_json::Value;
+
+use crate::camera::CameraBuilder;
+use crate::hittable::HittableList;
+use crate::material::{AlphaMode, Cutout, Lambertian, Material};
+use crate::mesh::TriangleMeshBuilder;
+use crate::principled::PrincipledBuilder;
+use crate::texture::{ChannelTexture, ImageTexture, SolidColor, Texture, TintedTexture};
+
+#[derive(Debug)]
+pub enum GltfError {
+    /// A file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The JSON part of the scene could not be parsed.
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    /// The scene is structurally invalid or uses an unsupported feature.
+    Invalid { path: PathBuf, message: String },
+    /// An image could not be decoded.
+    Texture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GltfError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            GltfError::Json { path, source } => {
+                write!(f, "{}: invalid JSON: {source}", path.display())
+            }
+            GltfError::Invalid { path, message } => write!(f, "{}: {message}", path.display()),
+            GltfError::Texture { path, source } => {
+                write!(f, "{}: failed to decode image: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for GltfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GltfError::Io { source, .. } => Some(source),
+            GltfError::Json { source, .. } => Some(source),
+            GltfError::Invalid { .. } => None,
+            GltfError::Texture { source, .. } => Some(source),
+        }
+    }
+}
+
+pub struct GltfScene {
+    /// All mesh primitives of the scene, in world space.
+    pub world: HittableList,
+    /// The perspective cameras of the scene, in the order they're found in the node hierarchy.
+    pub cameras: Vec<CameraBuilder>,
+}
+
+/// Load the default scene of a glTF file, or its first scene if none is marked as the default.
+pub fn load(path: impl AsRef<Path>) -> Result<GltfScene, GltfError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|source| GltfError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    Loader::new(path, &data)?.load()
+}
+
+const GLB_MAGIC: &[u8] = b"glTF";
+const GLB_CHUNK_JSON: u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN: u32 = 0x004E_4942;
+
+struct Loader<'a> {
+    path: &'a Path,
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+    /// Decoded images, by index and whether they were decoded from sRGB.
+    images: HashMap<(usize, bool), Arc<ImageTexture>>,
+    materials: HashMap<usize, Arc<dyn Material>>,
+    default_material: Arc<dyn Material>,
+}
+
+impl<'a> Loader<'a> {
+    fn new(path: &'a Path, data: &[u8]) -> Result<Self, GltfError> {
+        let mut loader = Self {
+            path,
+            json: Value::Null,
+            buffers: Vec::new(),
+            images: HashMap::new(),
+            materials: HashMap::new(),
+            default_material: Arc::new(Lambertian::new(Vector3::new(0.8, 0.8, 0.8))),
+        };
+
+        let (json, bin) = if data.starts_with(GLB_MAGIC) {
+            loader.split_glb(data)?
+        } else {
+            (data, None)
+        };
+        loader.json = serde_json::from_slice(json).map_err(|source| GltfError::Json {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let version = loader.json["asset"]["version"].as_str().unwrap_or("");
+        if !version.starts_with("2.") {
+            return Err(loader.invalid(format!("unsupported glTF version '{version}'")));
+        }
+
+        loader.buffers = array(&loader.json, "buffers")
+            .iter()
+            .enumerate()
+            .map(|(i, buffer)| {
+                let data = match buffer["uri"].as_str() {
+                    Some(uri) => loader.read_uri(uri)?,
+                    None if i == 0 => bin
+                        .map(<[u8]>::to_vec)
+                        .ok_or_else(|| loader.invalid("buffer 0 has no data".to_string()))?,
+                    None => return Err(loader.invalid(format!("buffer {i} has no uri"))),
+                };
+                let length = usize_field(buffer, "byteLength").unwrap_or(0);
+                if data.len() < length {
+                    return Err(loader.invalid(format!(
+                        "buffer {i} holds {} bytes, expected {length}",
+                        data.len()
+                    )));
+                }
+                Ok(data)
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(loader)
+    }
+
+    fn invalid(&self, message: String) -> GltfError {
+        GltfError::Invalid {
+            path: self.path.to_path_buf(),
+            message,
+        }
+    }
+
+    /// Split a binary glTF file into its JSON chunk and optional binary chunk.
+    fn split_glb<'d>(&self, data: &'d [u8]) -> Result<(&'d [u8], Option<&'d [u8]>), GltfError> {
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| self.invalid("truncated GLB file".to_string()))
+        };
+        if read_u32(4)? != 2 {
+            return Err(self.invalid("unsupported GLB container version".to_string()));
+        }
+
+        let mut json = None;
+        let mut bin = None;
+        let mut offset = 12;
+        while offset < data.len() {
+            let length = read_u32(offset)? as usize;
+            let kind = read_u32(offset + 4)?;
+            let chunk = data
+                .get(offset + 8..offset + 8 + length)
+                .ok_or_else(|| self.invalid("truncated GLB chunk".to_string()))?;
+            match kind {
+                GLB_CHUNK_JSON => json = Some(chunk),
+                GLB_CHUNK_BIN => bin = Some(chunk),
+                _ => {}
+            }
+            offset += 8 + length;
+        }
+
+        let json = json.ok_or_else(|| self.invalid("GLB file has no JSON chunk".to_string()))?;
+        Ok((json, bin))
+    }
+
+    /// Read the data behind a URI, which is either embedded base64 data or a relative path.
+    fn read_uri(&self, uri: &str) -> Result<Vec<u8>, GltfError> {
+        if let Some(data) = uri.strip_prefix("data:") {
+            let (_, encoded) = data
+                .split_once(";base64,")
+                .ok_or_else(|| self.invalid("data URI is not base64 encoded".to_string()))?;
+            return decode_base64(encoded)
+                .ok_or_else(|| self.invalid("invalid base64 in data URI".to_string()));
+        }
+        if uri.contains("://") {
+            return Err(self.invalid(format!("only local files are supported, got '{uri}'")));
+        }
+
+        let path = self
+            .path
+            .parent()
+            .unwrap_or(Path::new(""))
+            .join(percent_decode(uri));
+        fs::read(&path).map_err(|source| GltfError::Io { path, source })
+    }
+
+    fn load(mut self) -> Result<GltfScene, GltfError> {
+        let nodes = array(&self.json, "nodes").to_vec();
+        let roots: Vec<usize> = match array(&self.json, "scenes") {
+            [] => {
+                // Without scenes, every node that isn't the child of another is a root.
+                let children: Vec<usize> = nodes
+                    .iter()
+                    .flat_map(|node| usize_array(node, "children"))
+                    .collect();
+                (0..nodes.len()).filter(|i| !children.contains(i)).collect()
+            }
+            scenes => {
+                let index = usize_field(&self.json, "scene").unwrap_or(0);
+                let scene = scenes
+                    .get(index)
+                    .ok_or_else(|| self.invalid(format!("scene {index} does not exist")))?;
+                usize_array(scene, "nodes")
+            }
+        };
+
+        let mut scene = GltfScene {
+            world: HittableList::new(),
+            cameras: Vec::new(),
+        };
+        let mut stack: Vec<(usize, Matrix4<f32>, usize)> = roots
+            .into_iter()
+            .map(|node| (node, Matrix4::identity(), 0))
+            .collect();
+        while let Some((index, parent, depth)) = stack.pop() {
+            // Guard against cycles in malformed files.
+            if depth > nodes.len() {
+                return Err(self.invalid("node hierarchy contains a cycle".to_string()));
+            }
+            let node = nodes
+                .get(index)
+                .ok_or_else(|| self.invalid(format!("node {index} does not exist")))?;
+            let transform = parent * local_transform(node);
+
+            if let Some(mesh) = usize_field(node, "mesh") {
+                self.add_mesh(mesh, &transform, &mut scene.world)?;
+            }
+            if let Some(camera) = usize_field(node, "camera") {
+                if let Some(builder) = self.camera(camera, &transform)? {
+                    scene.cameras.push(builder);
+                }
+            }
+            for child in usize_array(node, "children").into_iter().rev() {
+                stack.push((child, transform, depth + 1));
+            }
+        }
+        Ok(scene)
+    }
+
+    fn add_mesh(
+        &mut self,
+        index: usize,
+        transform: &Matrix4<f32>,
+        world: &mut HittableList,
+    ) -> Result<(), GltfError> {
+        let mesh = self.item("meshes", index)?.clone();
+        let normal_matrix = transform
+            .fixed_view::<3, 3>(0, 0)
+            .try_inverse()
+            .unwrap_or_else(Matrix3::identity)
+            .transpose();
+        // Mirroring transforms turn the winding order of the faces around.
+        let flip_winding = transform.fixed_view::<3, 3>(0, 0).determinant() < 0.0;
+
+        for primitive in array(&mesh, "primitives") {
+            let mode = usize_field(primitive, "mode").unwrap_or(4);
+            if !(4..=6).contains(&mode) {
+                // Points and lines have no surface to render.
+                continue;
+            }
+
+            let attributes = &primitive["attributes"];
+            let position = usize_field(attributes, "POSITION")
+                .ok_or_else(|| self.invalid(format!("mesh {index} has no POSITION")))?;
+            let positions: Vec<Vector3<f32>> = self
+                .read_vectors::<3>(position)?
+                .into_iter()
+                .map(|p| transform.transform_point(&p.into()).coords)
+                .collect();
+
+            let (mat, tex_coord) = match usize_field(primitive, "material") {
+                Some(material) => (self.material(material)?, self.tex_coord(material)?),
+                None => (Arc::clone(&self.default_material), 0),
+            };
+
+            let mut builder = TriangleMeshBuilder::new(
+                positions.clone(),
+                self.primitive_indices(primitive, mode, positions.len(), flip_winding)?,
+            );
+            if let Some(normal) = usize_field(attributes, "NORMAL") {
+                let normals = self.read_vectors::<3>(normal)?;
+                self.check_vertex_count(normal, normals.len(), positions.len())?;
+                builder = builder.normals(normals.into_iter().map(|n| normal_matrix * n).collect());
+            }
+            if let Some(uv) = usize_field(attributes, &format!("TEXCOORD_{tex_coord}")) {
+                // glTF puts the origin of texture coordinates at the top left of the image.
+                let uvs = self.read_vectors::<2>(uv)?;
+                self.check_vertex_count(uv, uvs.len(), positions.len())?;
+                builder = builder.uvs(
+                    uvs.into_iter()
+                        .map(|t| Vector2::new(t.x, 1.0 - t.y))
+                        .collect(),
+                );
+            }
+            if let Some(color) = usize_field(attributes, "COLOR_0") {
+                let (values, components) = self.read_accessor(color)?;
+                if components != 3 && components != 4 {
+                    return Err(self.invalid(format!(
+                        "accessor {color} has {components} components, expected 3 or 4"
+                    )));
+                }
+                let colors: Vec<_> = values
+                    .chunks_exact(components)
+                    .map(|c| Vector3::new(c[0], c[1], c[2]))
+                    .collect();
+                self.check_vertex_count(color, colors.len(), positions.len())?;
+                builder = builder.colors(colors);
+            }
+            world.add(Box::new(builder.build(mat)));
+        }
+        Ok(())
+    }
+
+    /// Checks that a vertex attribute accessor holds one element per vertex.
+    fn check_vertex_count(
+        &self,
+        accessor: usize,
+        count: usize,
+        vertex_count: usize,
+    ) -> Result<(), GltfError> {
+        if count != vertex_count {
+            return Err(self.invalid(format!(
+                "accessor {accessor} has {count} elements, expected {vertex_count} vertices"
+            )));
+        }
+        Ok(())
+    }
+
+    fn primitive_indices(
+        &self,
+        primitive: &Value,
+        mode: usize,
+        vertex_count: usize,
+        flip_winding: bool,
+    ) -> Result<Vec<[u32; 3]>, GltfError> {
+        let indices: Vec<u32> = match usize_field(primitive, "indices") {
+            Some(accessor) => self
+                .read_accessor(accessor)?
+                .0
+                .into_iter()
+                .map(|i| i as u32)
+                .collect(),
+            None => (0..vertex_count as u32).collect(),
+        };
+        if let Some(&i) = indices.iter().find(|&&i| i as usize >= vertex_count) {
+            return Err(self.invalid(format!(
+                "vertex index {i} out of range, {vertex_count} vertices defined"
+            )));
+        }
+
+        let mut triangles: Vec<[u32; 3]> = match mode {
+            // Triangle strip, every other triangle has its winding reversed.
+            5 => (0..indices.len().saturating_sub(2))
+                .map(|i| {
+                    if i % 2 == 0 {
+                        [indices[i], indices[i + 1], indices[i + 2]]
+                    } else {
+                        [indices[i + 1], indices[i], indices[i + 2]]
+                    }
+                })
+                .collect(),
+            // Triangle fan.
+            6 => (1..indices.len().saturating_sub(1))
+                .map(|i| [indices[0], indices[i], indices[i + 1]])
+                .collect(),
+            _ => indices
+                .chunks_exact(3)
+                .map(|t| [t[0], t[1], t[2]])
+                .collect(),
+        };
+        if flip_winding {
+            for triangle in triangles.iter_mut() {
+                triangle.swap(1, 2);
+            }
+        }
+        Ok(triangles)
+    }
+
+    /// Map a glTF material onto a [`Principled`](crate::principled::Principled) material. The
+    /// core metallic-roughness model is supported along with the emissive strength, transmission,
+    /// IOR, volume attenuation, clearcoat, sheen and specular extensions. Masked and blended
+    /// materials are wrapped in a [`Cutout`].
+    fn material(&mut self, index: usize) -> Result<Arc<dyn Material>, GltfError> {
+        if let Some(mat) = self.materials.get(&index) {
+            return Ok(Arc::clone(mat));
+        }
+
+        let material = self.item("materials", index)?.clone();
+        let pbr = &material["pbrMetallicRoughness"];
+        let extensions = &material["extensions"];
+        let mut builder = PrincipledBuilder::new();
+
+        let base_color = f32_array::<4>(pbr, "baseColorFactor").unwrap_or([1.0; 4]);
+        let base_color = Vector3::new(base_color[0], base_color[1], base_color[2]);
+        builder = match usize_field(&pbr["baseColorTexture"], "index") {
+            Some(texture) => builder.base_color(tinted(self.texture(texture, true)?, base_color)),
+            None => builder.base_color(base_color),
+        };
+
+        // Roughness and metalness are packed in the green and blue channels of one texture.
+        let metallic = f32_field(pbr, "metallicFactor").unwrap_or(1.0);
+        let roughness = f32_field(pbr, "roughnessFactor").unwrap_or(1.0);
+        builder = match usize_field(&pbr["metallicRoughnessTexture"], "index") {
+            Some(texture) => {
+                let factors = Vector3::new(1.0, roughness, metallic);
+                let tex = tinted(self.texture(texture, false)?, factors);
+                builder
+                    .roughness(Arc::new(ChannelTexture::new(Arc::clone(&tex), 1)))
+                    .metallic(Arc::new(ChannelTexture::new(tex, 2)))
+            }
+            None => builder.roughness(roughness).metallic(metallic),
+        };
+
+        let strength = f32_field(
+            &extensions["KHR_materials_emissive_strength"],
+            "emissiveStrength",
+        )
+        .unwrap_or(1.0);
+        let emission = strength
+            * Vector3::from(f32_array::<3>(&material, "emissiveFactor").unwrap_or([0.0; 3]));
+        builder = match usize_field(&material["emissiveTexture"], "index") {
+            Some(texture) => builder.emission(tinted(self.texture(texture, true)?, emission)),
+            None => builder.emission(emission),
+        };
+
+        let transmission = &extensions["KHR_materials_transmission"];
+        let transmission_factor = f32_field(transmission, "transmissionFactor").unwrap_or(0.0);
+        builder = match usize_field(&transmission["transmissionTexture"], "index") {
+            Some(texture) => {
+                let tex = tinted(
+                    self.texture(texture, false)?,
+                    Vector3::repeat(transmission_factor),
+                );
+                builder.transmission(Arc::new(ChannelTexture::new(tex, 0)))
+            }
+            None => builder.transmission(transmission_factor),
+        };
+
+        let ior = f32_field(&extensions["KHR_materials_ior"], "ior").unwrap_or(1.5);
+        let volume = &extensions["KHR_materials_volume"];
+        let attenuation_color = f32_array::<3>(volume, "attenuationColor").unwrap_or([1.0; 3]);
+        let attenuation_distance =
+            f32_field(volume, "attenuationDistance").unwrap_or(f32::INFINITY);
+        if attenuation_distance <= 0.0 {
+            return Err(self.invalid(format!(
+                "material {index} has non-positive attenuation distance {attenuation_distance}"
+            )));
+        }
+        builder = builder
+            .ior(ior)
+            .color_at_distance(Vector3::from(attenuation_color), attenuation_distance);
+
+        // glTF derives the specular reflectance from the IOR, scaled by the specular factor.
+        let specular_factor =
+            f32_field(&extensions["KHR_materials_specular"], "specularFactor").unwrap_or(1.0);
+        let f0 = ((ior - 1.0) / (ior + 1.0)).powi(2) * specular_factor;
+        builder = builder.specular(f0 / 0.08);
+
+        let clearcoat = &extensions["KHR_materials_clearcoat"];
+        builder = builder
+            .clearcoat(f32_field(clearcoat, "clearcoatFactor").unwrap_or(0.0))
+            .clearcoat_roughness(f32_field(clearcoat, "clearcoatRoughnessFactor").unwrap_or(0.0));
+
+        let sheen_color = f32_array::<3>(&extensions["KHR_materials_sheen"], "sheenColorFactor")
+            .unwrap_or([0.0; 3]);
+        builder = builder.sheen(Vector3::from(sheen_color).max());
+
+        let mut mat: Arc<dyn Material> = Arc::new(builder.build());
+
+        // The opacity is the alpha of the base color.
+        let mode = match material["alphaMode"].as_str() {
+            Some("MASK") => Some(AlphaMode::Cutoff(
+                f32_field(&material, "alphaCutoff").unwrap_or(0.5),
+            )),
+            Some("BLEND") => Some(AlphaMode::Blend),
+            _ => None,
+        };
+        if let Some(mode) = mode {
+            let alpha =
+                Vector3::repeat(f32_array::<4>(pbr, "baseColorFactor").unwrap_or([1.0; 4])[3]);
+            let alpha: Arc<dyn Texture> = match usize_field(&pbr["baseColorTexture"], "index") {
+                Some(texture) => {
+                    Arc::new(self.texture(texture, true)?.alpha_channel().tinted(alpha))
+                }
+                None => Arc::new(SolidColor::new(alpha)),
+            };
+            mat = Arc::new(Cutout::new(mat, alpha, mode));
+        }
+        self.materials.insert(index, Arc::clone(&mat));
+        Ok(mat)
+    }
+
+    /// The texture coordinate set used by the textures of a material. Meshes hold a single set,
+    /// so all the textures must use the same one.
+    fn tex_coord(&self, material: usize) -> Result<usize, GltfError> {
+        let item = self.item("materials", material)?;
+        let pbr = &item["pbrMetallicRoughness"];
+        let textures = [
+            &pbr["baseColorTexture"],
+            &pbr["metallicRoughnessTexture"],
+            &item["emissiveTexture"],
+            &item["extensions"]["KHR_materials_transmission"]["transmissionTexture"],
+        ];
+        let mut sets = textures
+            .iter()
+            .filter(|texture| usize_field(texture, "index").is_some())
+            .map(|texture| usize_field(texture, "texCoord").unwrap_or(0));
+
+        let first = sets.next().unwrap_or(0);
+        if let Some(other) = sets.find(|&set| set != first) {
+            return Err(self.invalid(format!(
+                "material {material} mixes texture coordinate sets {first} and {other}"
+            )));
+        }
+        Ok(first)
+    }
+
+    /// Load a texture, decoding it from sRGB if it holds colors rather than other data. Images
+    /// are decoded once and shared by all the textures using them.
+    fn texture(&mut self, index: usize, srgb: bool) -> Result<Arc<ImageTexture>, GltfError> {
+        let texture = self.item("textures", index)?;
+        let source = usize_field(texture, "source")
+            .ok_or_else(|| self.invalid(format!("texture {index} has no source")))?;
+        if let Some(image) = self.images.get(&(source, srgb)) {
+            return Ok(Arc::clone(image));
+        }
+
+        let image = self.item("images", source)?;
+        let bytes = match (image["uri"].as_str(), usize_field(image, "bufferView")) {
+            (Some(uri), _) => self.read_uri(uri)?,
+            (None, Some(view)) => self.buffer_view(view)?.0.to_vec(),
+            (None, None) => return Err(self.invalid(format!("image {source} has no data"))),
+        };
+        let texture = Arc::new(
+            if srgb {
+                ImageTexture::from_memory(&bytes)
+            } else {
+                ImageTexture::from_memory_linear(&bytes)
+            }
+            .map_err(|source| GltfError::Texture {
+                path: self.path.to_path_buf(),
+                source,
+            })?,
+        );
+        self.images.insert((source, srgb), Arc::clone(&texture));
+        Ok(texture)
+    }
+
+    fn camera(
+        &self,
+        index: usize,
+        transform: &Matrix4<f32>,
+    ) -> Result<Option<CameraBuilder>, GltfError> {
+        let camera = self.item("cameras", index)?;
+        if camera["type"].as_str() != Some("perspective") {
+            return Ok(None);
+        }
+
+        // glTF cameras look down their local -Z axis, with +Y pointing up.
+        let perspective = &camera["perspective"];
+        let lookfrom = transform.transform_point(&Vector3::zeros().into()).coords;
+        let forward = transform.transform_vector(&Vector3::new(0.0, 0.0, -1.0));
+        let vup = transform.transform_vector(&Vector3::new(0.0, 1.0, 0.0));
+
+        let mut builder = CameraBuilder::new()
+            .vfov(f32_field(perspective, "yfov").unwrap_or(0.8).to_degrees())
+            .lookfrom(lookfrom)
+            .lookat(lookfrom + forward)
+            .vup(vup)
+            .defocus_angle(0.0);
+        if let Some(aspect_ratio) = f32_field(perspective, "aspectRatio") {
+            builder = builder.aspect_ratio(aspect_ratio);
+        }
+        Ok(Some(builder))
+    }
+
+    fn item(&self, kind: &str, index: usize) -> Result<&Value, GltfError> {
+        array(&self.json, kind)
+            .get(index)
+            .ok_or_else(|| self.invalid(format!("{kind} {index} does not exist")))
+    }
+
+    /// Returns the bytes of a buffer view along with its stride, if it has one.
+    fn buffer_view(&self, index: usize) -> Result<(&[u8], Option<usize>), GltfError> {
+        let view = self.item("bufferViews", index)?;
+        let buffer = usize_field(view, "buffer").unwrap_or(0);
+        let offset = usize_field(view, "byteOffset").unwrap_or(0);
+        let length = usize_field(view, "byteLength").unwrap_or(0);
+        let data = self
+            .buffers
+            .get(buffer)
+            .and_then(|data| data.get(offset..offset + length))
+            .ok_or_else(|| self.invalid(format!("buffer view {index} is out of bounds")))?;
+        Ok((data, usize_field(view, "byteStride")))
+    }
+
+    /// Read all elements of an accessor as floats, returning them along with the number of
+    /// components per element. Normalized integers are mapped to [0, 1] or [-1, 1].
+    fn read_accessor(&self, index: usize) -> Result<(Vec<f32>, usize), GltfError> {
+        let accessor = self.item("accessors", index)?;
+        if !accessor["sparse"].is_null() {
+            return Err(self.invalid(format!("accessor {index} is sparse")));
+        }
+
+        let components = match accessor["type"].as_str() {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            other => return Err(self.invalid(format!("unsupported accessor type {other:?}"))),
+        };
+        let component_type = usize_field(accessor, "componentType").unwrap_or(0);
+        let (component_size, max) = match component_type {
+            5120 => (1, i8::MAX as f32),
+            5121 => (1, u8::MAX as f32),
+            5122 => (2, i16::MAX as f32),
+            5123 => (2, u16::MAX as f32),
+            5125 => (4, u32::MAX as f32),
+            5126 => (4, 1.0),
+            _ => return Err(self.invalid(format!("unsupported component type {component_type}"))),
+        };
+        let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+        let count = usize_field(accessor, "count").unwrap_or(0);
+
+        let Some(view) = usize_field(accessor, "bufferView") else {
+            // Accessors without a buffer view are initialized with zeros.
+            return Ok((vec![0.0; count * components], components));
+        };
+        let (data, stride) = self.buffer_view(view)?;
+        let element_size = components * component_size;
+        let stride = stride.unwrap_or(element_size);
+        let offset = usize_field(accessor, "byteOffset").unwrap_or(0);
+        if count > 0 && offset + (count - 1) * stride + element_size > data.len() {
+            return Err(self.invalid(format!("accessor {index} is out of bounds")));
+        }
+
+        let mut values = Vec::with_capacity(count * components);
+        for element in 0..count {
+            for component in 0..components {
+                let at = offset + element * stride + component * component_size;
+                let b = &data[at..at + component_size];
+                let value = match component_type {
+                    5120 => b[0] as i8 as f32,
+                    5121 => b[0] as f32,
+                    5122 => i16::from_le_bytes([b[0], b[1]]) as f32,
+                    5123 => u16::from_le_bytes([b[0], b[1]]) as f32,
+                    5125 => u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32,
+                    _ => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+                };
+                values.push(if normalized {
+                    (value / max).max(-1.0)
+                } else {
+                    value
+                });
+            }
+        }
+        Ok((values, components))
+    }
+
+    fn read_vectors<const N: usize>(
+        &self,
+        index: usize,
+    ) -> Result<Vec<nalgebra::SVector<f32, N>>, GltfError> {
+        let (values, components) = self.read_accessor(index)?;
+        if components != N {
+            return Err(self.invalid(format!(
+                "accessor {index} has {components} components, expected {N}"
+            )));
+        }
+        Ok(values
+            .chunks_exact(N)
+            .map(nalgebra::SVector::<f32, N>::from_column_slice)
+            .collect())
+    }
+}
+
+/// An image multiplied by the factor a material applies to it.
+fn tinted(image: Arc<ImageTexture>, factor: Vector3<f32>) -> Arc<dyn Texture> {
+    if factor == Vector3::repeat(1.0) {
+        image
+    } else {
+        Arc::new(TintedTexture::new(image, factor))
+    }
+}
+
+/// The transform of a node relative to its parent, given either as a matrix or as separate
+/// translation, rotation and scale.
+fn local_transform(node: &Value) -> Matrix4<f32> {
+    if let Some(matrix) = f32_array::<16>(node, "matrix") {
+        return Matrix4::from_column_slice(&matrix);
+    }
+
+    let t = f32_array::<3>(node, "translation").unwrap_or([0.0; 3]);
+    let r = f32_array::<4>(node, "rotation").unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let s = f32_array::<3>(node, "scale").unwrap_or([1.0; 3]);
+
+    let rotation = UnitQuaternion::from_quaternion(Quaternion::from(Vector4::from(r)));
+    Matrix4::new_translation(&Vector3::from(t))
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(&Vector3::from(s))
+}
+
+fn array<'v>(value: &'v Value, key: &str) -> &'v [Value] {
+    value[key].as_array().map_or(&[], Vec::as_slice)
+}
+
+fn usize_field(value: &Value, key: &str) -> Option<usize> {
+    value[key].as_u64().map(|v| v as usize)
+}
+
+fn usize_array(value: &Value, key: &str) -> Vec<usize> {
+    array(value, key)
+        .iter()
+        .filter_map(|v| v.as_u64().map(|v| v as usize))
+        .collect()
+}
+
+fn f32_field(value: &Value, key: &str) -> Option<f32> {
+    value[key].as_f64().map(|v| v as f32)
+}
+
+fn f32_array<const N: usize>(value: &Value, key: &str) -> Option<[f32; N]> {
+    let values = array(value, key);
+    if values.len() != N {
+        return None;
+    }
+    let mut result = [0.0; N];
+    for (r, v) in result.iter_mut().zip(values) {
+        *r = v.as_f64()? as f32;
+    }
+    Some(result)
+}
+
+fn decode_base64(encoded: &str) -> Option<Vec<u8>> {
+    fn sextet(c: u8) -> Option<u32> {
+        match c {
+            b'A'..=b'Z' => Some((c - b'A') as u32),
+            b'a'..=b'z' => Some((c - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((c - b'0' + 52) as u32),
+            b'+' | b'-' => Some(62),
+            b'/' | b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    let encoded = encoded.trim_end_matches('=').as_bytes();
+    let mut decoded = Vec::with_capacity(encoded.len() * 3 / 4);
+    for chunk in encoded.chunks(4) {
+        let mut bits = 0u32;
+        for &c in chunk {
+            bits = (bits << 6) | sextet(c)?;
+        }
+        // A partial chunk carries 6 bits per character, of which only whole bytes are kept.
+        bits <<= 6 * (4 - chunk.len());
+        let bytes = bits.to_be_bytes();
+        decoded.extend_from_slice(&bytes[1..chunk.len()]);
+    }
+    Some(decoded)
+}
+
+fn percent_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            if let Some(byte) = uri
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
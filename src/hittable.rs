@@ -2,6 +2,7 @@ use std::sync::Arc;
 
 use nalgebra::Vector3;
 
+use crate::aabb::Aabb;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
@@ -11,6 +12,15 @@ pub struct HitRecord {
     pub normal: Vector3<f32>,
     pub mat: Arc<dyn Material>,
     pub t: f32,
+    /// Surface coordinates of the hit point, in the range [0, 1].
+    pub u: f32,
+    pub v: f32,
+    /// Vertex color interpolated at the hit point, tinting whatever the material scatters. White
+    /// for surfaces without vertex colors.
+    pub color: Vector3<f32>,
+    /// Direction in which `u` increases along the surface, orienting anisotropic materials. Zero
+    /// for surfaces without a natural direction.
+    pub tangent: Vector3<f32>,
     pub front_face: bool,
 }
 
@@ -21,6 +31,10 @@ impl HitRecord {
             normal: Vector3::new(0.0, 0.0, 0.0),
             mat,
             t,
+            u: 0.0,
+            v: 0.0,
+            color: Vector3::new(1.0, 1.0, 1.0),
+            tangent: Vector3::new(0.0, 0.0, 0.0),
             front_face: false,
         }
     }
@@ -37,24 +51,66 @@ impl HitRecord {
     }
 }
 
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+    fn bounding_box(&self) -> Aabb;
+
+    /// The fraction of light travelling along the ray within `ray_t` that passes through the
+    /// object, for shadow rays. By default, surfaces let light through their transparent parts
+    /// and block it elsewhere, even glass. Volumes estimate how much light they let through
+    /// instead, and objects holding others combine their transmittances.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        let mut transmittance = 1.0;
+        let mut t_min = ray_t.min;
+        while let Some(rec) = self.hit(r, Interval::new(t_min, ray_t.max)) {
+            let opacity = rec.mat.opacity(rec.u, rec.v, &rec.p);
+            transmittance *= 1.0 - opacity.clamp(0.0, 1.0);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+            // Step past the surface like past the origin of scattered rays.
+            t_min = rec.t + 0.001;
+        }
+        transmittance
+    }
 }
 
 pub struct HittableList {
     objects: Vec<Box<dyn Hittable>>,
+    bbox: Aabb,
 }
 
 impl HittableList {
     pub fn new() -> Self {
         Self {
             objects: Vec::new(),
+            bbox: Aabb::EMPTY,
         }
     }
 
     pub fn add(&mut self, object: Box<dyn Hittable>) {
+        self.bbox = Aabb::enclosing(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Consumes the list, returning the contained objects.
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
+}
+
+impl Default for HittableList {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Hittable for HittableList {
@@ -69,4 +125,19 @@ impl Hittable for HittableList {
         }
         hit_anything
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        let mut transmittance = 1.0;
+        for object in self.objects.iter() {
+            transmittance *= object.transmittance(r, ray_t);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
 }
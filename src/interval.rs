@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug)]
 pub struct Interval {
     pub min: f32,
     pub max: f32,
@@ -11,17 +12,25 @@ impl Interval {
     //     }
     // }
 
-    pub fn new(min: f32, max: f32) -> Self {
+    pub const fn new(min: f32, max: f32) -> Self {
         Self { min, max }
     }
 
-    // fn size(&self) -> f64 {
-    //     self.max - self.min
-    // }
+    /// Creates the interval tightly enclosing the two input intervals.
+    pub fn enclosing(a: Interval, b: Interval) -> Self {
+        Self {
+            min: a.min.min(b.min),
+            max: a.max.max(b.max),
+        }
+    }
 
-    // fn contains(&self, x: f64) -> bool {
-    //     self.min <= x && x <= self.max
-    // }
+    pub fn size(&self) -> f32 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, x: f32) -> bool {
+        self.min <= x && x <= self.max
+    }
 
     pub fn surrounds(&self, x: f32) -> bool {
         self.min < x && x < self.max
@@ -37,16 +46,12 @@ impl Interval {
         x
     }
 
-    // fn empty() -> Self {
-    //     Self {
-    //         min: f64::INFINITY,
-    //         max: f64::NEG_INFINITY,
-    //     }
-    // }
-    // fn universe() -> Self {
-    //     Self {
-    //         min: f64::NEG_INFINITY,
-    //         max: f64::INFINITY,
-    //     }
-    // }
+    /// Returns a copy of the interval padded by `delta / 2` on both sides.
+    pub fn expand(&self, delta: f32) -> Self {
+        let padding = delta / 2.0;
+        Self::new(self.min - padding, self.max + padding)
+    }
+
+    pub const EMPTY: Interval = Interval::new(f32::INFINITY, f32::NEG_INFINITY);
+    pub const UNIVERSE: Interval = Interval::new(f32::NEG_INFINITY, f32::INFINITY);
 }
@@ -0,0 +1,36 @@
+pub mod aabb;
+pub mod bvh;
+pub mod camera;
+pub mod capsule;
+pub mod color;
+pub mod cone;
+pub mod cuboid;
+pub mod cylinder;
+pub mod environment;
+pub mod gltf;
+pub mod hittable;
+pub mod interval;
+pub mod light;
+pub mod material;
+pub mod medium;
+pub mod mesh;
+pub mod microfacet;
+pub mod obj;
+pub mod onb;
+pub mod perlin;
+pub mod plane;
+pub mod ply;
+pub mod principled;
+pub mod quad;
+pub mod random_utils;
+pub mod ray;
+pub mod sided;
+pub mod sky;
+pub mod spectrum;
+pub mod sphere;
+pub mod stl;
+pub mod texture;
+pub mod torus;
+pub mod transform;
+pub mod triangle;
+pub mod voxel;
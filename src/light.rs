@@ -0,0 +1,207 @@
+//! Light sources that are not part of the scene geometry: point, spot and directional lights.
+//!
+//! Rays never hit these lights, so they are only seen through direct lighting: at each
+//! scattering event, the camera samples every light, traces a shadow ray towards it and adds
+//! its contribution weighted by [`Material::eval`](crate::material::Material::eval). Perfectly
+//! specular materials can't reflect them: [`Dielectric`] glass, [`Metal`] without fuzz and
+//! [`ThinFilm`] over a dielectric base. [`Subsurface`] objects don't reflect them off their smooth
+//! surface either, but are lit where light leaves them after scattering inside.
+//!
+//! [`Dielectric`]: crate::material::Dielectric
+//! [`Metal`]: crate::material::Metal
+//! [`ThinFilm`]: crate::material::ThinFilm
+//! [`Subsurface`]: crate::medium::Subsurface
+
+use std::f32::consts::PI;
+
+use nalgebra::Vector3;
+
+use crate::onb::Onb;
+use crate::random_utils::random_float;
+
+/// The light arriving at a point from one sample of a light source.
+pub struct LightSample {
+    /// Unit direction from the lit point towards the light.
+    pub direction: Vector3<f32>,
+    /// Distance to the light along `direction`, infinite for directional lights.
+    pub distance: f32,
+    /// The incoming radiance divided by the density of sampling `direction`. For delta lights,
+    /// this is the irradiance on a surface facing the light.
+    pub radiance: Vector3<f32>,
+}
+
+pub trait Light: Send + Sync {
+    /// Samples the light arriving at the point `p`, or `None` if no light reaches it.
+    fn sample(&self, p: &Vector3<f32>) -> Option<LightSample>;
+}
+
+/// How the light of a [`PointLight`] or a [`SpotLight`] fades with the distance.
+#[derive(Clone, Copy, Debug)]
+pub enum Falloff {
+    /// Physically based falloff with the square of the distance.
+    InverseSquare,
+    /// Falloff with the distance, softer than in reality.
+    Linear,
+    /// No falloff at all.
+    Constant,
+}
+
+impl Falloff {
+    fn attenuation(&self, distance: f32) -> f32 {
+        match self {
+            Falloff::InverseSquare => 1.0 / (distance * distance),
+            Falloff::Linear => 1.0 / distance,
+            Falloff::Constant => 1.0,
+        }
+    }
+}
+
+/// A light emitting in all directions from a single point, like a bare light bulb.
+pub struct PointLight {
+    position: Vector3<f32>,
+    intensity: Vector3<f32>,
+    falloff: Falloff,
+}
+
+impl PointLight {
+    /// Creates a light of the given radiant intensity, i.e. the power emitted per steradian,
+    /// which is the irradiance at a distance of one with inverse square falloff.
+    pub fn new(position: Vector3<f32>, intensity: Vector3<f32>) -> Self {
+        Self {
+            position,
+            intensity,
+            falloff: Falloff::InverseSquare,
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+}
+
+impl Light for PointLight {
+    fn sample(&self, p: &Vector3<f32>) -> Option<LightSample> {
+        let (direction, distance) = towards(p, &self.position)?;
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: self.intensity * self.falloff.attenuation(distance),
+        })
+    }
+}
+
+/// A point light emitting within a cone, like a stage light or a flashlight.
+pub struct SpotLight {
+    position: Vector3<f32>,
+    /// Unit direction of the cone axis.
+    direction: Vector3<f32>,
+    intensity: Vector3<f32>,
+    /// Cosine of the half angle of the cone.
+    cos_cone: f32,
+    /// Cosine of the half angle within which the light is at full intensity.
+    cos_full: f32,
+    falloff: Falloff,
+}
+
+impl SpotLight {
+    /// Creates a light at `position` shining towards `direction`, with the radiant intensity
+    /// `intensity` along its axis. `cone_angle` is the full opening angle of the cone in
+    /// degrees, and the light fades out smoothly over its outer `soft_edge` fraction, from 0 for
+    /// a hard edge to 1 for a light fading all the way from the axis.
+    pub fn new(
+        position: Vector3<f32>,
+        direction: Vector3<f32>,
+        intensity: Vector3<f32>,
+        cone_angle: f32,
+        soft_edge: f32,
+    ) -> Self {
+        let half_angle = 0.5 * cone_angle.clamp(0.0, 360.0).to_radians();
+        Self {
+            position,
+            direction: direction.normalize(),
+            intensity,
+            cos_cone: half_angle.cos(),
+            cos_full: (half_angle * (1.0 - soft_edge.clamp(0.0, 1.0))).cos(),
+            falloff: Falloff::InverseSquare,
+        }
+    }
+
+    pub fn with_falloff(mut self, falloff: Falloff) -> Self {
+        self.falloff = falloff;
+        self
+    }
+}
+
+impl Light for SpotLight {
+    fn sample(&self, p: &Vector3<f32>) -> Option<LightSample> {
+        let (direction, distance) = towards(p, &self.position)?;
+        let cos_theta = -direction.dot(&self.direction);
+        if cos_theta <= self.cos_cone {
+            return None;
+        }
+        let edge = if cos_theta >= self.cos_full {
+            1.0
+        } else {
+            let t = (cos_theta - self.cos_cone) / (self.cos_full - self.cos_cone);
+            t * t * (3.0 - 2.0 * t)
+        };
+        Some(LightSample {
+            direction,
+            distance,
+            radiance: edge * self.falloff.attenuation(distance) * self.intensity,
+        })
+    }
+}
+
+/// Light arriving from a very distant source in one direction, like the sun. The source covers a
+/// small disk in the sky, which gives shadows soft edges.
+pub struct DirectionalLight {
+    /// The sky disk of the source, with `w` pointing towards it.
+    frame: Onb,
+    irradiance: Vector3<f32>,
+    /// Cosine of the angular radius of the source.
+    cos_radius: f32,
+}
+
+impl DirectionalLight {
+    /// Creates a light travelling along `direction`, giving the irradiance `irradiance` to
+    /// surfaces facing it. `angular_diameter` is the apparent size of the source in degrees,
+    /// about 0.53 for the sun, or zero for perfectly sharp shadows.
+    pub fn new(direction: Vector3<f32>, irradiance: Vector3<f32>, angular_diameter: f32) -> Self {
+        Self {
+            frame: Onb::new(&-direction),
+            irradiance,
+            cos_radius: (0.5 * angular_diameter.clamp(0.0, 180.0))
+                .to_radians()
+                .cos(),
+        }
+    }
+}
+
+impl Light for DirectionalLight {
+    fn sample(&self, _p: &Vector3<f32>) -> Option<LightSample> {
+        // Sample the cone of directions towards the source uniformly, which makes its radiance
+        // over the sampling density equal to the irradiance.
+        let cos_theta = 1.0 - random_float() * (1.0 - self.cos_radius);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_float();
+        let direction = self.frame.transform(&Vector3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+        Some(LightSample {
+            direction,
+            distance: f32::INFINITY,
+            radiance: self.irradiance,
+        })
+    }
+}
+
+/// The unit direction and distance from `p` to `position`, or `None` if they coincide.
+fn towards(p: &Vector3<f32>, position: &Vector3<f32>) -> Option<(Vector3<f32>, f32)> {
+    let offset = position - p;
+    let distance = offset.magnitude();
+    (distance > 0.0).then(|| (offset / distance, distance))
+}
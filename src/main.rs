@@ -2,21 +2,15 @@ use std::sync::Arc;
 
 use nalgebra::Vector3;
 
-use hittable::HittableList;
-use material::{Dielectric, Lambertian, Metal};
-use random_utils::{random_float, random_float_range, random_vector, random_vector_range};
-use sphere::Sphere;
-
-use crate::camera::CameraBuilder;
-
-mod camera;
-mod color;
-mod hittable;
-mod interval;
-mod material;
-mod random_utils;
-mod ray;
-mod sphere;
+use ray_tracing_in_one_weekend::bvh::BvhNode;
+use ray_tracing_in_one_weekend::camera::CameraBuilder;
+use ray_tracing_in_one_weekend::hittable::HittableList;
+use ray_tracing_in_one_weekend::material::{Dielectric, Lambertian, Metal};
+use ray_tracing_in_one_weekend::plane::Plane;
+use ray_tracing_in_one_weekend::random_utils::{
+    random_float, random_float_range, random_vector, random_vector_range,
+};
+use ray_tracing_in_one_weekend::sphere::Sphere;
 
 fn test_scene(world: &mut HittableList) {
     for a in -11..11 {
@@ -53,13 +47,6 @@ fn test_scene(world: &mut HittableList) {
 fn main() {
     let mut world = HittableList::new();
 
-    let ground_material = Arc::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
-    world.add(Box::new(Sphere::new(
-        Vector3::new(0.0, -1000.0, 0.0),
-        1000.0,
-        ground_material,
-    )));
-
     test_scene(&mut world);
 
     let material1 = Arc::new(Dielectric::new(1.5));
@@ -96,5 +83,15 @@ fn main() {
         .focus_dist(10.0)
         .build();
 
-    cam.render(&mut world);
+    // The ground plane is unbounded, so it's kept next to the BVH rather than inside it.
+    let ground_material = Arc::new(Lambertian::new(Vector3::new(0.5, 0.5, 0.5)));
+    let mut scene = HittableList::new();
+    scene.add(Box::new(Plane::new(
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        ground_material,
+    )));
+    scene.add(Box::new(BvhNode::from(world)));
+
+    cam.render(&mut scene);
 }
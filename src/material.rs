@@ -1,30 +1,75 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
 use nalgebra::Vector3;
 
 use crate::hittable::HitRecord;
-use crate::random_utils::random_vector_range;
+use crate::microfacet;
+use crate::onb::Onb;
+use crate::random_utils::{random_cosine_direction, random_float, random_unit_vector};
 use crate::ray::Ray;
+use crate::spectrum::Wavelengths;
+use crate::texture::{IntoTexture, SolidColor, Texture};
 
 pub struct ScatterResult {
     pub attenuation: Vector3<f32>,
     pub scattered: Ray,
+    /// Where the scattered ray leaves the surface when that isn't the hit point, as for light
+    /// scattered below the surface of a [`Subsurface`](crate::medium::Subsurface) object. The
+    /// exit is lit directly with its own material, whose scattering the attenuation applies to.
+    pub exit: Option<HitRecord>,
 }
 
 pub trait Material: Send + Sync {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult>;
+
+    /// Returns the light emitted at the surface coordinates `u`, `v` of the point `p`. Most
+    /// materials don't emit any light.
+    fn emitted(&self, _u: f32, _v: f32, _p: &Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the probability in [0, 1] that a ray hitting the surface at the surface coordinates
+    /// `u`, `v` of the point `p` stops there rather than passing through. Most materials are
+    /// opaque.
+    fn opacity(&self, _u: f32, _v: f32, _p: &Vector3<f32>) -> f32 {
+        1.0
+    }
+
+    /// Returns the fraction of light arriving from the unit direction `wi` that the surface
+    /// scatters along the ray `r_in` back towards its origin, i.e. the scattering function times
+    /// the cosine of `wi` to the normal, or the phase function for media. Used to light surfaces
+    /// directly from light sources. Perfectly specular materials can't scatter light from a given
+    /// direction, and return zero like the default.
+    fn eval(&self, _r_in: &Ray, _rec: &HitRecord, _wi: &Vector3<f32>) -> Vector3<f32> {
+        Vector3::zeros()
+    }
+
+    /// Returns the density over solid angle with which [`Material::scatter`] picks the unit
+    /// direction `wi`, or an approximation of it, used to weight light sampled from the
+    /// environment against scattered rays finding it. Materials returning zero from
+    /// [`Material::eval`] return zero here too, like the default.
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _wi: &Vector3<f32>) -> f32 {
+        0.0
+    }
 }
 
 pub struct Lambertian {
-    albedo: Vector3<f32>,
+    tex: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo: Vector3<f32>) -> Self {
-        Self { albedo }
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
         let mut scatter_direction = rec.normal + random_unit_vector();
 
         if near_zero(&scatter_direction) {
@@ -32,10 +77,20 @@ impl Material for Lambertian {
         }
 
         Some(ScatterResult {
-            attenuation: self.albedo,
-            scattered: Ray::new(rec.p, scatter_direction),
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scattered: Ray::new(rec.p, scatter_direction, r_in.time()),
+            exit: None,
         })
     }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        let cos_theta = rec.normal.dot(wi).max(0.0);
+        self.tex.value(rec.u, rec.v, &rec.p) * cos_theta / PI
+    }
+
+    fn pdf(&self, _r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        rec.normal.dot(wi).max(0.0) / PI
+    }
 }
 
 pub struct Metal {
@@ -47,6 +102,30 @@ impl Metal {
     pub fn new(albedo: Vector3<f32>, fuzz: f32) -> Self {
         Self { albedo, fuzz }
     }
+
+    /// The density over solid angle of the fuzzy reflection of `r_in` leaving along `wi`, or
+    /// zero for a perfect mirror. Reflections going below the surface are absorbed rather than
+    /// renormalized, so the density integrates to less than one over the hemisphere.
+    fn fuzz_pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        if self.fuzz <= 0.0 || wi.dot(&rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        // Scattered directions point to the sphere of radius `fuzz` around the tip of the unit
+        // mirror direction, sampled uniformly by area. Sum over the points where `wi` crosses
+        // the sphere, at distances `t`, the area density converted to solid angle.
+        let mirror = reflect(&r_in.direction(), &rec.normal).normalize();
+        let cos = wi.dot(&mirror);
+        let discriminant = cos * cos - (1.0 - self.fuzz * self.fuzz);
+        if discriminant <= 0.0 {
+            return 0.0;
+        }
+        let root = discriminant.sqrt();
+        [cos - root, cos + root]
+            .iter()
+            .filter(|&&t| t > 0.0)
+            .map(|t| t * t / (4.0 * PI * self.fuzz * root))
+            .sum()
+    }
 }
 
 impl Material for Metal {
@@ -54,25 +133,75 @@ impl Material for Metal {
         let mut reflected = reflect(&r_in.direction(), &rec.normal);
         reflected = reflected.normalize() + self.fuzz * random_unit_vector();
 
-        Some(Ray::new(rec.p, reflected))
+        Some(Ray::new(rec.p, reflected, r_in.time()))
             .filter(|ray| ray.direction().dot(&rec.normal) > 0.0)
             .map(|scattered| ScatterResult {
                 attenuation: self.albedo,
                 scattered,
+                exit: None,
             })
     }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        // Scattered rays are weighted by the albedo alone, so the scattering function times the
+        // cosine is the albedo times their density.
+        self.fuzz_pdf(r_in, rec, wi) * self.albedo
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        self.fuzz_pdf(r_in, rec, wi)
+    }
 }
 
 pub struct Dielectric {
     /// Refractive index in vacuum or air, or the ratio of the material's refractive index over the
     /// refractive index of the enclosing media
     refraction_index: f32,
+    /// Absorption coefficient per unit distance travelled inside the material, for each color
+    /// channel. Zero for clear glass.
+    absorption: Vector3<f32>,
+    /// Variation of the refractive index with the wavelength, used instead of
+    /// `refraction_index` in spectral renders.
+    dispersion: Option<Dispersion>,
 }
 
 impl Dielectric {
     pub fn new(refraction_index: f32) -> Self {
-        Self { refraction_index }
+        Self {
+            refraction_index,
+            absorption: Vector3::zeros(),
+            dispersion: None,
+        }
+    }
+
+    /// Creates a material whose refractive index varies with the wavelength, splitting white
+    /// light into its colors in spectral renders. RGB renders use the index at the sodium D line
+    /// (589.3 nm), as catalogs do.
+    pub fn dispersive(dispersion: Dispersion) -> Self {
+        Self {
+            dispersion: Some(dispersion),
+            ..Self::new(dispersion.refraction_index(589.3))
+        }
+    }
+
+    /// Absorb light inside the material following the Beer–Lambert law, so that the fraction
+    /// `exp(-absorption * d)` of it remains after travelling the distance `d`.
+    pub fn with_absorption(mut self, absorption: Vector3<f32>) -> Self {
+        self.absorption = absorption;
+        self
     }
+
+    /// Absorb light inside the material so that white light has taken on the color `color`
+    /// after travelling the distance `distance`. This is usually easier to pick than an
+    /// absorption coefficient.
+    ///
+    /// Panics if `distance` is not positive.
+    pub fn with_color_at_distance(self, color: Vector3<f32>, distance: f32) -> Self {
+        assert!(distance > 0.0, "color distance must be positive");
+        let absorption = color.map(|c| -c.clamp(1e-6, 1.0).ln() / distance);
+        self.with_absorption(absorption)
+    }
+
     /// Use Schlick's approximation for reflectance.
     fn reflectance(cosine: f32, refraction_index: f32) -> f32 {
         let mut r0 = (1.0 - refraction_index) / (1.0 + refraction_index);
@@ -83,10 +212,19 @@ impl Dielectric {
 
 impl Material for Dielectric {
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        // Each wavelength refracts in its own direction, so only the hero wavelength can follow
+        // the scattered ray.
+        let (refraction_index, wavelengths) = match (self.dispersion, r_in.wavelengths()) {
+            (Some(dispersion), Some(wavelengths)) => (
+                dispersion.refraction_index(wavelengths.hero()),
+                Some(wavelengths.terminate_secondary()),
+            ),
+            _ => (self.refraction_index, None),
+        };
         let ri = if rec.front_face {
-            1.0 / self.refraction_index
+            1.0 / refraction_index
         } else {
-            self.refraction_index
+            refraction_index
         };
 
         let unit_direction = r_in.direction().normalize();
@@ -101,13 +239,840 @@ impl Material for Dielectric {
                 refract(&unit_direction, &rec.normal, ri)
             };
 
+        // A ray hitting the back face has travelled through the inside of the material.
+        let attenuation = if rec.front_face {
+            Vector3::new(1.0, 1.0, 1.0)
+        } else {
+            let distance = rec.t * r_in.direction().magnitude();
+            self.absorption.map(|a| (-a * distance).exp())
+        };
+
+        let mut scattered = Ray::new(rec.p, direction, r_in.time());
+        if let Some(wavelengths) = wavelengths {
+            scattered = scattered.with_wavelengths(wavelengths);
+        }
         Some(ScatterResult {
-            attenuation: Vector3::new(1.0, 1.0, 1.0),
-            scattered: Ray::new(rec.p, direction),
+            attenuation,
+            scattered,
+            exit: None,
         })
     }
 }
 
+/// The refractive index of a transparent material as a function of the wavelength, from one of
+/// the empirical formulas of optical glass catalogs. Wavelengths are in micrometers in the
+/// coefficients, but in nanometers in [`Dispersion::refraction_index`].
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    /// Cauchy's equation, `n = a + b / λ²`.
+    Cauchy { a: f32, b: f32 },
+    /// The Sellmeier equation, `n² = 1 + Σ b[i] λ² / (λ² - c[i])`.
+    Sellmeier { b: [f32; 3], c: [f32; 3] },
+}
+
+impl Dispersion {
+    /// Schott N-BK7, the most common optical glass.
+    pub const BK7: Dispersion = Dispersion::Sellmeier {
+        b: [1.039_612, 0.231_792_34, 1.010_469_5],
+        c: [0.006_000_699, 0.020_017_914, 103.560_65],
+    };
+
+    /// Fused silica, i.e. pure quartz glass, after Malitson (1965).
+    pub const FUSED_SILICA: Dispersion = Dispersion::Sellmeier {
+        b: [0.696_166_3, 0.407_942_6, 0.897_479_4],
+        c: [0.004_679_148, 0.013_512_063, 97.934],
+    };
+
+    /// Diamond, whose strong dispersion gives its colorful fire, after Peter (1923).
+    pub const DIAMOND: Dispersion = Dispersion::Sellmeier {
+        b: [0.3306, 4.3356, 0.0],
+        c: [0.030_625, 0.011_236, 0.0],
+    };
+
+    /// The refractive index at the wavelength `lambda`, in nanometers.
+    pub fn refraction_index(&self, lambda: f32) -> f32 {
+        let lambda2 = (lambda * 1e-3).powi(2);
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / lambda2,
+            Dispersion::Sellmeier { b, c } => {
+                let sum: f32 = (0..3).map(|i| b[i] * lambda2 / (lambda2 - c[i])).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
+/// A rough surface made of GGX microfacets, in the metallic-roughness parameterization. Metals
+/// reflect light tinted by their base color, while dielectrics (`metallic` = 0) combine a white
+/// glossy reflection with diffuse scattering of the base color.
+pub struct Microfacet {
+    base_color: Arc<dyn Texture>,
+    metallic: f32,
+    roughness: f32,
+}
+
+impl Microfacet {
+    pub fn new(base_color: Vector3<f32>, metallic: f32, roughness: f32) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(base_color)), metallic, roughness)
+    }
+
+    pub fn from_texture(base_color: Arc<dyn Texture>, metallic: f32, roughness: f32) -> Self {
+        Self {
+            base_color,
+            metallic: metallic.clamp(0.0, 1.0),
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+}
+
+impl Material for Microfacet {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+
+        let base_color = self.base_color.value(rec.u, rec.v, &rec.p);
+        let f0 = Vector3::repeat(DIELECTRIC_F0).lerp(&base_color, self.metallic);
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        let m =
+            microfacet::sample_visible_normal(&wo, alpha, alpha, random_float(), random_float());
+        let fresnel = microfacet::fresnel_schlick(&f0, wo.dot(&m));
+
+        // Pick the glossy or the diffuse lobe in proportion to their weights.
+        let diffuse = (1.0 - self.metallic) * (1.0 - fresnel.max()) * base_color;
+        let total_weight = fresnel.max() + diffuse.max();
+        if total_weight <= 0.0 {
+            return None;
+        }
+        let specular_probability = fresnel.max() / total_weight;
+
+        let (wi, attenuation) = if random_float() < specular_probability {
+            let wi = 2.0 * wo.dot(&m) * m - wo;
+            if wi.z <= 0.0 {
+                return None;
+            }
+            let masking =
+                microfacet::g2(&wo, &wi, alpha, alpha) / microfacet::g1(&wo, alpha, alpha);
+            (wi, fresnel * masking / specular_probability)
+        } else {
+            (
+                random_cosine_direction(),
+                diffuse / (1.0 - specular_probability),
+            )
+        };
+
+        Some(ScatterResult {
+            attenuation,
+            scattered: Ray::new(rec.p, onb.transform(&wi), r_in.time()),
+            exit: None,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+        let wi = onb.to_basis(wi);
+        if wi.z <= 0.0 {
+            return Vector3::zeros();
+        }
+
+        let base_color = self.base_color.value(rec.u, rec.v, &rec.p);
+        let f0 = Vector3::repeat(DIELECTRIC_F0).lerp(&base_color, self.metallic);
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        let m = (wo + wi).normalize();
+        let fresnel = microfacet::fresnel_schlick(&f0, wo.dot(&m));
+        let specular =
+            fresnel * microfacet::d(&m, alpha, alpha) * microfacet::g2(&wo, &wi, alpha, alpha)
+                / (4.0 * wo.z);
+        let diffuse = (1.0 - self.metallic) * (1.0 - fresnel.max()) * base_color * wi.z / PI;
+        specular + diffuse
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+        let wi = onb.to_basis(wi);
+        if wi.z <= 0.0 {
+            return 0.0;
+        }
+
+        // The lobe is picked with the Fresnel reflectance of the sampled microfacet, which is
+        // approximated here by the one of the half vector.
+        let base_color = self.base_color.value(rec.u, rec.v, &rec.p);
+        let f0 = Vector3::repeat(DIELECTRIC_F0).lerp(&base_color, self.metallic);
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        let fresnel = microfacet::fresnel_schlick(&f0, wo.dot(&(wo + wi).normalize()));
+        let diffuse = (1.0 - self.metallic) * (1.0 - fresnel.max()) * base_color;
+        let total_weight = fresnel.max() + diffuse.max();
+        if total_weight <= 0.0 {
+            return 0.0;
+        }
+        let specular_probability = fresnel.max() / total_weight;
+        specular_probability * microfacet::reflection_pdf(&wo, &wi, alpha, alpha)
+            + (1.0 - specular_probability) * wi.z / PI
+    }
+}
+
+/// A rough metal described by its complex index of refraction `eta + i k` for each color channel,
+/// reflecting light with the exact Fresnel equations for conductors over GGX microfacets.
+pub struct Conductor {
+    eta: Vector3<f32>,
+    k: Vector3<f32>,
+    roughness: f32,
+}
+
+/// Approximate complex indices of refraction of common metals at the red, green and blue
+/// wavelengths (650, 550 and 450 nm), as (symbol, name, eta, k).
+const CONDUCTORS: [(&str, &str, [f32; 3], [f32; 3]); 6] = [
+    ("Au", "gold", [0.143, 0.374, 1.442], [3.983, 2.385, 1.603]),
+    ("Ag", "silver", [0.155, 0.117, 0.138], [4.828, 3.122, 2.147]),
+    ("Cu", "copper", [0.200, 0.924, 1.102], [3.912, 2.452, 2.142]),
+    (
+        "Al",
+        "aluminium",
+        [1.657, 0.880, 0.521],
+        [9.224, 6.270, 4.837],
+    ),
+    (
+        "Cr",
+        "chromium",
+        [3.110, 3.180, 2.320],
+        [3.320, 3.330, 3.150],
+    ),
+    (
+        "Ti",
+        "titanium",
+        [2.740, 2.540, 2.270],
+        [3.810, 3.430, 3.040],
+    ),
+];
+
+impl Conductor {
+    pub fn new(eta: Vector3<f32>, k: Vector3<f32>, roughness: f32) -> Self {
+        Self {
+            eta,
+            k,
+            roughness: roughness.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Looks up a metal by its chemical symbol or English name, ignoring case: Au (gold),
+    /// Ag (silver), Cu (copper), Al (aluminium), Cr (chromium) or Ti (titanium).
+    pub fn named(name: &str, roughness: f32) -> Option<Self> {
+        CONDUCTORS
+            .iter()
+            .find(|(symbol, full_name, _, _)| {
+                symbol.eq_ignore_ascii_case(name)
+                    || full_name.eq_ignore_ascii_case(name)
+                    || (*full_name == "aluminium" && name.eq_ignore_ascii_case("aluminum"))
+            })
+            .map(|&(_, _, eta, k)| Self::new(Vector3::from(eta), Vector3::from(k), roughness))
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        let onb = Onb::new(&rec.normal);
+        scatter_rough_reflection(r_in, rec, &onb, (alpha, alpha), |cos_theta| {
+            microfacet::fresnel_conductor(&self.eta, &self.k, cos_theta)
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        let onb = Onb::new(&rec.normal);
+        eval_rough_reflection(r_in, &onb, (alpha, alpha), wi, |cos_theta| {
+            microfacet::fresnel_conductor(&self.eta, &self.k, cos_theta)
+        })
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        let alpha = microfacet::roughness_to_alpha(self.roughness);
+        pdf_rough_reflection(r_in, &Onb::new(&rec.normal), (alpha, alpha), wi)
+    }
+}
+
+/// A thin transparent film, such as soap, oil or an oxide layer, coating a dielectric or a metal.
+/// Light reflected on the top and the bottom of the film interferes, giving iridescent colors
+/// that change with the viewing angle and the thickness of the film.
+pub struct ThinFilm {
+    /// Film thickness in nanometers, read from the first channel.
+    thickness: Arc<dyn Texture>,
+    film_ior: f32,
+    base: FilmBase,
+}
+
+enum FilmBase {
+    Dielectric {
+        refraction_index: f32,
+        absorption: Vector3<f32>,
+    },
+    Conductor {
+        eta: Vector3<f32>,
+        k: Vector3<f32>,
+        roughness: f32,
+    },
+}
+
+/// Wavelengths in nanometers averaged over for the red, green and blue channels of the film
+/// reflectance when rendering in RGB, smoothing out the fringes of thick films.
+const FILM_WAVELENGTHS: [[f32; 4]; 3] = [
+    [595.0, 625.0, 655.0, 685.0],
+    [505.0, 530.0, 555.0, 580.0],
+    [420.0, 440.0, 460.0, 480.0],
+];
+
+impl ThinFilm {
+    /// Coats a glass-like material, e.g. `Dielectric::new(1.0)` for a soap bubble, whose film
+    /// has air on both sides. `thickness` is in nanometers, and interference colors appear
+    /// between about 100 and 1000 nm.
+    pub fn over_dielectric(base: &Dielectric, film_ior: f32, thickness: impl IntoTexture) -> Self {
+        Self {
+            thickness: thickness.into_texture(),
+            film_ior,
+            base: FilmBase::Dielectric {
+                refraction_index: base.refraction_index,
+                absorption: base.absorption,
+            },
+        }
+    }
+
+    /// Coats a metal given by its complex index of refraction, e.g. for anodized metal.
+    pub fn over_conductor(base: &Conductor, film_ior: f32, thickness: impl IntoTexture) -> Self {
+        Self {
+            thickness: thickness.into_texture(),
+            film_ior,
+            base: FilmBase::Conductor {
+                eta: base.eta,
+                k: base.k,
+                roughness: base.roughness,
+            },
+        }
+    }
+
+    /// Coats a [`Metal`], whose albedo is taken as its reflectance at normal incidence and whose
+    /// fuzz is taken as its roughness.
+    pub fn over_metal(base: &Metal, film_ior: f32, thickness: impl IntoTexture) -> Self {
+        // Find the complex index of refraction with the albedo as its reflectance at normal
+        // incidence, following Gulbrandsen, "Artist Friendly Metallic Fresnel" (2014), with an
+        // edge tint of white.
+        let r = base.albedo.map(|r| r.clamp(0.0, 0.99));
+        let eta = r.map(|r| (1.0 - r) / (1.0 + r));
+        let k = r.zip_map(&eta, |r, n| {
+            ((r * (n + 1.0).powi(2) - (n - 1.0).powi(2)) / (1.0 - r))
+                .max(0.0)
+                .sqrt()
+        });
+        Self {
+            thickness: thickness.into_texture(),
+            film_ior,
+            base: FilmBase::Conductor {
+                eta,
+                k,
+                roughness: base.fuzz.clamp(0.0, 1.0).sqrt(),
+            },
+        }
+    }
+
+    /// The reflectance of the coated surface for each color channel, where `eta_film`,
+    /// `eta_base`, `k_base` and `wavelength_scale` are relative to the incident medium.
+    ///
+    /// Given `wavelengths`, the reflectance is that of the hero wavelength for all channels, with
+    /// the base taking the index of refraction of the channel whose band is nearest.
+    fn reflectance(
+        &self,
+        cos_theta: f32,
+        thickness: f32,
+        (eta_film, eta_base, k_base): (f32, &Vector3<f32>, &Vector3<f32>),
+        wavelength_scale: f32,
+        wavelengths: Option<Wavelengths>,
+    ) -> Vector3<f32> {
+        let at = |channel: usize, wavelength: f32| {
+            microfacet::fresnel_thin_film(
+                cos_theta,
+                eta_film,
+                thickness,
+                eta_base[channel],
+                k_base[channel],
+                wavelength * wavelength_scale,
+            )
+        };
+        if let Some(wavelengths) = wavelengths {
+            let hero = wavelengths.hero();
+            let channel = if hero >= 590.0 {
+                0
+            } else if hero >= 495.0 {
+                1
+            } else {
+                2
+            };
+            return Vector3::repeat(at(channel, hero));
+        }
+        Vector3::from_fn(|i, _| {
+            let total: f32 = FILM_WAVELENGTHS[i]
+                .iter()
+                .map(|&wavelength| at(i, wavelength))
+                .sum();
+            total / FILM_WAVELENGTHS[i].len() as f32
+        })
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let thickness = self.thickness.value(rec.u, rec.v, &rec.p).x.max(0.0);
+        // The interference differs for each wavelength, so only the hero wavelength can follow
+        // the scattered ray.
+        let wavelengths = r_in.wavelengths();
+        let mut result = match &self.base {
+            FilmBase::Conductor { eta, k, roughness } => {
+                let alpha = microfacet::roughness_to_alpha(*roughness);
+                let onb = Onb::new(&rec.normal);
+                scatter_rough_reflection(r_in, rec, &onb, (alpha, alpha), |cos_theta| {
+                    self.reflectance(
+                        cos_theta,
+                        thickness,
+                        (self.film_ior, eta, k),
+                        1.0,
+                        wavelengths,
+                    )
+                })?
+            }
+            FilmBase::Dielectric {
+                refraction_index,
+                absorption,
+            } => {
+                // Seen from inside, the film lies between the material and the outside air.
+                let (n_incident, n_base) = if rec.front_face {
+                    (1.0, *refraction_index)
+                } else {
+                    (*refraction_index, 1.0)
+                };
+                let unit_direction = r_in.direction().normalize();
+                let cos_theta = -unit_direction.dot(&rec.normal).min(1.0);
+                let reflectance = self.reflectance(
+                    cos_theta,
+                    thickness,
+                    (
+                        self.film_ior / n_incident,
+                        &Vector3::repeat(n_base / n_incident),
+                        &Vector3::zeros(),
+                    ),
+                    1.0 / n_incident,
+                    wavelengths,
+                );
+
+                // The film has parallel sides, so the refracted direction is the same as without
+                // it. Choose between reflection and refraction by the average reflectance, and
+                // weight by the actual one of each channel.
+                let ri = n_incident / n_base;
+                let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+                let reflect_probability = if ri * sin_theta > 1.0 {
+                    1.0
+                } else {
+                    reflectance.mean().clamp(0.0, 1.0)
+                };
+                let (direction, weight) = if random_float() < reflect_probability {
+                    (
+                        reflect(&unit_direction, &rec.normal),
+                        reflectance / reflect_probability,
+                    )
+                } else {
+                    (
+                        refract(&unit_direction, &rec.normal, ri),
+                        (Vector3::new(1.0, 1.0, 1.0) - reflectance) / (1.0 - reflect_probability),
+                    )
+                };
+
+                // A ray hitting the back face has travelled through the inside of the material.
+                let attenuation = if rec.front_face {
+                    weight
+                } else {
+                    let distance = rec.t * r_in.direction().magnitude();
+                    weight.component_mul(&absorption.map(|a| (-a * distance).exp()))
+                };
+
+                ScatterResult {
+                    attenuation,
+                    scattered: Ray::new(rec.p, direction, r_in.time()),
+                    exit: None,
+                }
+            }
+        };
+        if let Some(wavelengths) = wavelengths {
+            result.scattered = result
+                .scattered
+                .with_wavelengths(wavelengths.terminate_secondary());
+        }
+        Some(result)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        // Coated dielectrics are perfectly specular.
+        let FilmBase::Conductor { eta, k, roughness } = &self.base else {
+            return Vector3::zeros();
+        };
+        let thickness = self.thickness.value(rec.u, rec.v, &rec.p).x.max(0.0);
+        // Light sampled here reaches all the wavelengths of the path, which a single reflectance
+        // per channel can only give as the RGB average, unless the hero wavelength is left alone.
+        let wavelengths = r_in.wavelengths().filter(Wavelengths::is_hero_only);
+        let alpha = microfacet::roughness_to_alpha(*roughness);
+        let onb = Onb::new(&rec.normal);
+        eval_rough_reflection(r_in, &onb, (alpha, alpha), wi, |cos_theta| {
+            self.reflectance(
+                cos_theta,
+                thickness,
+                (self.film_ior, eta, k),
+                1.0,
+                wavelengths,
+            )
+        })
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        let FilmBase::Conductor { roughness, .. } = &self.base else {
+            return 0.0;
+        };
+        let alpha = microfacet::roughness_to_alpha(*roughness);
+        pdf_rough_reflection(r_in, &Onb::new(&rec.normal), (alpha, alpha), wi)
+    }
+}
+
+/// A metal with stretched highlights, such as brushed aluminium, whose GGX roughness differs along
+/// and across a direction on the surface.
+///
+/// The highlights stretch along the rougher direction. The direction of `roughness_x`
+/// follows the surface tangent, where texture coordinate `u` increases, rotated by an angle and
+/// optionally redirected by a direction map.
+pub struct Anisotropic {
+    base_color: Arc<dyn Texture>,
+    roughness_x: f32,
+    roughness_y: f32,
+    rotation: f32,
+    direction_map: Option<Arc<dyn Texture>>,
+}
+
+impl Anisotropic {
+    /// Creates a metal reflecting `base_color` at normal incidence, with the perceptual roughness
+    /// `roughness_x` along the tangent and `roughness_y` across it.
+    pub fn new(base_color: impl IntoTexture, roughness_x: f32, roughness_y: f32) -> Self {
+        Self {
+            base_color: base_color.into_texture(),
+            roughness_x: roughness_x.clamp(0.0, 1.0),
+            roughness_y: roughness_y.clamp(0.0, 1.0),
+            rotation: 0.0,
+            direction_map: None,
+        }
+    }
+
+    /// Rotates the direction of roughness `roughness_x` about the normal by `degrees`,
+    /// counterclockwise when looking at the surface.
+    pub fn with_rotation(mut self, degrees: f32) -> Self {
+        self.rotation = degrees.to_radians();
+        self
+    }
+
+    /// Reads the direction of roughness `roughness_x` from a texture, with the red and green
+    /// channels mapped from [0, 1] to the [-1, 1] components along and across the tangent, as in
+    /// flow maps. The rotation is applied on top.
+    pub fn with_direction_map(mut self, direction_map: Arc<dyn Texture>) -> Self {
+        self.direction_map = Some(direction_map);
+        self
+    }
+
+    /// The shading frame at the hit, with `u` along the direction of roughness `roughness_x`.
+    fn frame(&self, rec: &HitRecord) -> Onb {
+        let surface = Onb::from_tangent(&rec.normal, &rec.tangent);
+        let mut direction = Vector3::new(1.0, 0.0, 0.0);
+        if let Some(map) = &self.direction_map {
+            let texel = map.value(rec.u, rec.v, &rec.p);
+            let mapped = Vector3::new(2.0 * texel.x - 1.0, 2.0 * texel.y - 1.0, 0.0);
+            if mapped.magnitude_squared() > 1e-12 {
+                direction = mapped;
+            }
+        }
+        let (sin, cos) = self.rotation.sin_cos();
+        let direction = Vector3::new(
+            cos * direction.x - sin * direction.y,
+            sin * direction.x + cos * direction.y,
+            0.0,
+        );
+        Onb::from_tangent(&rec.normal, &surface.transform(&direction))
+    }
+
+    fn alphas(&self) -> (f32, f32) {
+        (
+            microfacet::roughness_to_alpha(self.roughness_x),
+            microfacet::roughness_to_alpha(self.roughness_y),
+        )
+    }
+}
+
+impl Material for Anisotropic {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let f0 = self.base_color.value(rec.u, rec.v, &rec.p);
+        scatter_rough_reflection(r_in, rec, &self.frame(rec), self.alphas(), |cos_theta| {
+            microfacet::fresnel_schlick(&f0, cos_theta)
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        let f0 = self.base_color.value(rec.u, rec.v, &rec.p);
+        eval_rough_reflection(r_in, &self.frame(rec), self.alphas(), wi, |cos_theta| {
+            microfacet::fresnel_schlick(&f0, cos_theta)
+        })
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        pdf_rough_reflection(r_in, &self.frame(rec), self.alphas(), wi)
+    }
+}
+
+/// How a [`Cutout`] turns the values of its alpha mask into opacity.
+#[derive(Clone, Copy, Debug)]
+pub enum AlphaMode {
+    /// Fully opaque where the alpha is at least the cutoff, and fully transparent elsewhere.
+    Cutoff(f32),
+    /// Partially transparent, letting rays through at random with a probability of one minus
+    /// the alpha.
+    Blend,
+}
+
+/// Cuts holes in another material following an alpha mask, so that a single quad can model a
+/// leaf or a fence. Rays pass through the holes as if the surface wasn't there.
+pub struct Cutout {
+    mat: Arc<dyn Material>,
+    /// Opacity in the first channel, e.g. from [`ImageTexture::alpha_channel`].
+    ///
+    /// [`ImageTexture::alpha_channel`]: crate::texture::ImageTexture::alpha_channel
+    alpha: Arc<dyn Texture>,
+    mode: AlphaMode,
+}
+
+impl Cutout {
+    pub fn new(mat: Arc<dyn Material>, alpha: Arc<dyn Texture>, mode: AlphaMode) -> Self {
+        Self { mat, alpha, mode }
+    }
+}
+
+impl Material for Cutout {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        self.mat.scatter(r_in, rec)
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32> {
+        self.mat.emitted(u, v, p)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        self.mat.eval(r_in, rec, wi)
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        self.mat.pdf(r_in, rec, wi)
+    }
+
+    fn opacity(&self, u: f32, v: f32, p: &Vector3<f32>) -> f32 {
+        let alpha = self.alpha.value(u, v, p).x * self.mat.opacity(u, v, p);
+        match self.mode {
+            AlphaMode::Cutoff(cutoff) if alpha >= cutoff => 1.0,
+            AlphaMode::Cutoff(_) => 0.0,
+            AlphaMode::Blend => alpha.clamp(0.0, 1.0),
+        }
+    }
+}
+
+/// Reflectance at normal incidence of common dielectrics, with a refractive index around 1.5.
+const DIELECTRIC_F0: f32 = 0.04;
+
+/// An emissive material, turning any surface into an area light.
+pub struct DiffuseLight {
+    tex: Arc<dyn Texture>,
+}
+
+impl DiffuseLight {
+    pub fn new(emit: Vector3<f32>) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(emit)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn scatter(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<ScatterResult> {
+        None
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32> {
+        self.tex.value(u, v, p)
+    }
+}
+
+/// The phase function of a participating medium scattering light equally in all directions.
+pub struct Isotropic {
+    tex: Arc<dyn Texture>,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Vector3<f32>) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Self {
+        Self { tex }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        Some(ScatterResult {
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scattered: Ray::new(rec.p, random_unit_vector(), r_in.time()),
+            exit: None,
+        })
+    }
+
+    fn eval(&self, _r_in: &Ray, rec: &HitRecord, _wi: &Vector3<f32>) -> Vector3<f32> {
+        self.tex.value(rec.u, rec.v, &rec.p) / (4.0 * PI)
+    }
+
+    fn pdf(&self, _r_in: &Ray, _rec: &HitRecord, _wi: &Vector3<f32>) -> f32 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+/// The Henyey–Greenstein phase function, for media that favor scattering forwards (`g` > 0, e.g.
+/// haze and clouds) or backwards (`g` < 0).
+pub struct HenyeyGreenstein {
+    tex: Arc<dyn Texture>,
+    /// Asymmetry parameter in (-1, 1): the average cosine of the scattering angle.
+    g: f32,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Vector3<f32>, g: f32) -> Self {
+        Self::from_texture(Arc::new(SolidColor::new(albedo)), g)
+    }
+
+    pub fn from_texture(tex: Arc<dyn Texture>, g: f32) -> Self {
+        Self {
+            tex,
+            g: g.clamp(-0.999, 0.999),
+        }
+    }
+
+    /// Samples the cosine of the angle between the incoming and the scattered direction.
+    fn sample_cos_theta(&self) -> f32 {
+        let xi = random_float();
+        if self.g.abs() < 1e-3 {
+            return 1.0 - 2.0 * xi;
+        }
+        let g = self.g;
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * xi);
+        ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let cos_theta = self.sample_cos_theta();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * random_float();
+        let direction = Onb::new(&r_in.direction()).transform(&Vector3::new(
+            sin_theta * phi.cos(),
+            sin_theta * phi.sin(),
+            cos_theta,
+        ));
+
+        Some(ScatterResult {
+            attenuation: self.tex.value(rec.u, rec.v, &rec.p),
+            scattered: Ray::new(rec.p, direction, r_in.time()),
+            exit: None,
+        })
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        self.pdf(r_in, rec, wi) * self.tex.value(rec.u, rec.v, &rec.p)
+    }
+
+    fn pdf(&self, r_in: &Ray, _rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        // Scattered directions are sampled exactly following the phase function.
+        let g = self.g;
+        let cos_theta = r_in.direction().normalize().dot(wi);
+        (1.0 - g * g) / (4.0 * PI * (1.0 + g * g - 2.0 * g * cos_theta).powf(1.5))
+    }
+}
+
+/// Reflects the ray about a GGX microfacet normal sampled among the visible ones, with the
+/// Fresnel reflectance given as a function of the cosine between the ray and the microfacet.
+/// The roughness `alpha_x` and `alpha_y` are along the `u` and `v` axes of the shading frame
+/// `onb`, whose `w` is the surface normal.
+fn scatter_rough_reflection(
+    r_in: &Ray,
+    rec: &HitRecord,
+    onb: &Onb,
+    (alpha_x, alpha_y): (f32, f32),
+    fresnel: impl Fn(f32) -> Vector3<f32>,
+) -> Option<ScatterResult> {
+    let wo = outgoing_direction(r_in, onb);
+    let m =
+        microfacet::sample_visible_normal(&wo, alpha_x, alpha_y, random_float(), random_float());
+    let wi = 2.0 * wo.dot(&m) * m - wo;
+    if wi.z <= 0.0 {
+        return None;
+    }
+
+    let masking =
+        microfacet::g2(&wo, &wi, alpha_x, alpha_y) / microfacet::g1(&wo, alpha_x, alpha_y);
+    Some(ScatterResult {
+        attenuation: fresnel(wo.dot(&m)) * masking,
+        scattered: Ray::new(rec.p, onb.transform(&wi), r_in.time()),
+        exit: None,
+    })
+}
+
+/// The GGX reflection sampled by [`scatter_rough_reflection`] of light arriving from the world
+/// direction `wi`, times the cosine of `wi`.
+fn eval_rough_reflection(
+    r_in: &Ray,
+    onb: &Onb,
+    (alpha_x, alpha_y): (f32, f32),
+    wi: &Vector3<f32>,
+    fresnel: impl Fn(f32) -> Vector3<f32>,
+) -> Vector3<f32> {
+    let wo = outgoing_direction(r_in, onb);
+    let wi = onb.to_basis(wi);
+    if wi.z <= 0.0 {
+        return Vector3::zeros();
+    }
+    let m = (wo + wi).normalize();
+    fresnel(wo.dot(&m))
+        * microfacet::d(&m, alpha_x, alpha_y)
+        * microfacet::g2(&wo, &wi, alpha_x, alpha_y)
+        / (4.0 * wo.z)
+}
+
+/// The density with which [`scatter_rough_reflection`] picks the world direction `wi`.
+fn pdf_rough_reflection(
+    r_in: &Ray,
+    onb: &Onb,
+    (alpha_x, alpha_y): (f32, f32),
+    wi: &Vector3<f32>,
+) -> f32 {
+    let wo = outgoing_direction(r_in, onb);
+    microfacet::reflection_pdf(&wo, &onb.to_basis(wi), alpha_x, alpha_y)
+}
+
+/// The direction back along the ray `r_in` in the shading frame `onb`, kept above the surface.
+pub(crate) fn outgoing_direction(r_in: &Ray, onb: &Onb) -> Vector3<f32> {
+    // Interpolated normals can face slightly away from the viewer near silhouettes.
+    let mut wo = onb.to_basis(&-r_in.direction().normalize());
+    wo.z = wo.z.max(1e-4);
+    wo.normalize()
+}
+
 fn reflect(v: &Vector3<f32>, n: &Vector3<f32>) -> Vector3<f32> {
     v - 2.0 * v.dot(n) * n
 }
@@ -119,16 +1084,6 @@ fn refract(uv: &Vector3<f32>, n: &Vector3<f32>, etai_over_etat: f32) -> Vector3<
     r_out_perp + r_out_parallel
 }
 
-fn random_unit_vector() -> Vector3<f32> {
-    loop {
-        let p: Vector3<f32> = random_vector_range(-1.0, 1.0);
-        let lensq = p.magnitude_squared();
-        if 1e-160 < lensq && lensq <= 1.0 {
-            return p / lensq.sqrt();
-        }
-    }
-}
-
 /// Return `true` if the vector is close to zero in all dimensions.
 fn near_zero(v: &Vector3<f32>) -> bool {
     const S: f32 = 1e-8;
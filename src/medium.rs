@@ -0,0 +1,472 @@
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::{DiffuseLight, Isotropic, Lambertian, Material, ScatterResult};
+use crate::microfacet;
+use crate::random_utils::{random_float, random_unit_vector};
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::voxel::VoxelGrid;
+
+/// A volume of uniform density filling a closed boundary, such as fog or smoke.
+///
+/// Rays passing through the volume scatter at a random distance, with the probability of
+/// scattering growing with the density and the distance travelled inside. The boundary is
+/// assumed to be convex: a ray is only considered inside between its first two boundary hits.
+pub struct ConstantMedium {
+    boundary: Box<dyn Hittable>,
+    neg_inv_density: f32,
+    phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// Creates an isotropic medium of the given color.
+    pub fn new(boundary: Box<dyn Hittable>, density: f32, albedo: Vector3<f32>) -> Self {
+        Self::with_phase_function(boundary, density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn from_texture(boundary: Box<dyn Hittable>, density: f32, tex: Arc<dyn Texture>) -> Self {
+        Self::with_phase_function(boundary, density, Arc::new(Isotropic::from_texture(tex)))
+    }
+
+    /// Creates a medium scattering light according to the given phase function material, e.g.
+    /// [`HenyeyGreenstein`](crate::material::HenyeyGreenstein).
+    pub fn with_phase_function(
+        boundary: Box<dyn Hittable>,
+        density: f32,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let rec1 = self.boundary.hit(r, Interval::UNIVERSE)?;
+        let rec2 = self
+            .boundary
+            .hit(r, Interval::new(rec1.t + 0.0001, f32::INFINITY))?;
+
+        let t_min = rec1.t.max(ray_t.min).max(0.0);
+        let t_max = rec2.t.min(ray_t.max);
+        if t_min >= t_max {
+            return None;
+        }
+
+        scatter_record(r, t_min, t_max, self.neg_inv_density, &self.phase_function)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.boundary.bounding_box()
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        let Some(rec1) = self.boundary.hit(r, Interval::UNIVERSE) else {
+            return 1.0;
+        };
+        let Some(rec2) = self
+            .boundary
+            .hit(r, Interval::new(rec1.t + 0.0001, f32::INFINITY))
+        else {
+            return 1.0;
+        };
+
+        let t_min = rec1.t.max(ray_t.min).max(0.0);
+        let t_max = rec2.t.min(ray_t.max);
+        if t_min >= t_max {
+            return 1.0;
+        }
+        let distance = (t_max - t_min) * r.direction().magnitude();
+        (distance / self.neg_inv_density).exp()
+    }
+}
+
+/// A heterogeneous volume such as a cloud or an explosion, with its density given by a voxel grid
+/// stretched over an axis-aligned box.
+///
+/// Collisions are found by delta tracking: tentative collisions are sampled against the maximum
+/// density, and each one is randomly accepted as absorption, scattering or a null collision in
+/// proportion to the local coefficients. Absorption ends the path, picking up the light emitted
+/// at that point if the medium has an emission grid. Shadow rays are attenuated by ratio
+/// tracking, which weights them by the fraction of null collisions rather than stopping them at
+/// the first real one.
+pub struct GridMedium {
+    bounds: Aabb,
+    density: Arc<VoxelGrid>,
+    sigma_a: f32,
+    sigma_s: f32,
+    phase_function: Arc<dyn Material>,
+    emission: Option<(Arc<VoxelGrid>, Vector3<f32>)>,
+    /// The material of absorption events in media without emission.
+    absorber: Arc<dyn Material>,
+}
+
+impl GridMedium {
+    /// Creates an isotropic medium filling the box with the opposite corners `a` and `b`. The
+    /// absorption and scattering coefficients `sigma_a` and `sigma_s` are per unit of grid
+    /// density.
+    pub fn new(
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        density: Arc<VoxelGrid>,
+        sigma_a: f32,
+        sigma_s: f32,
+    ) -> Self {
+        Self {
+            bounds: Aabb::from_points(a, b),
+            density,
+            sigma_a,
+            sigma_s,
+            phase_function: Arc::new(Isotropic::new(Vector3::new(1.0, 1.0, 1.0))),
+            emission: None,
+            absorber: Arc::new(DiffuseLight::new(Vector3::zeros())),
+        }
+    }
+
+    /// Scatter light according to the given phase function material instead of isotropically.
+    pub fn with_phase_function(mut self, phase_function: Arc<dyn Material>) -> Self {
+        self.phase_function = phase_function;
+        self
+    }
+
+    /// Make absorbing collisions emit `color` scaled by the value of `grid`, e.g. a temperature
+    /// grid for fire.
+    pub fn with_emission(mut self, grid: Arc<VoxelGrid>, color: Vector3<f32>) -> Self {
+        self.emission = Some((grid, color));
+        self
+    }
+
+    /// An upper bound of the extinction coefficient anywhere in the medium.
+    fn majorant(&self) -> f32 {
+        self.density.max_value() * (self.sigma_a + self.sigma_s)
+    }
+
+    fn grid_coords(&self, p: &Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(
+            (p.x - self.bounds.x.min) / self.bounds.x.size(),
+            (p.y - self.bounds.y.min) / self.bounds.y.size(),
+            (p.z - self.bounds.z.min) / self.bounds.z.size(),
+        )
+    }
+
+    fn density_at(&self, p: &Vector3<f32>) -> f32 {
+        self.density.sample(&self.grid_coords(p)).max(0.0)
+    }
+}
+
+impl Hittable for GridMedium {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let inside = self.bounds.overlap(r, ray_t)?;
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        let step_scale = 1.0 / (majorant * r.direction().magnitude());
+        let mut t = inside.min;
+        loop {
+            t -= (1.0 - random_float()).ln() * step_scale;
+            if t >= inside.max {
+                return None;
+            }
+
+            let p = r.at(t);
+            let density = self.density_at(&p);
+            let xi = majorant * random_float();
+            if xi < density * self.sigma_a {
+                let mat = match &self.emission {
+                    Some((grid, color)) => {
+                        let emission = grid.sample(&self.grid_coords(&p)).max(0.0) * color;
+                        Arc::new(DiffuseLight::new(emission))
+                    }
+                    None => Arc::clone(&self.absorber),
+                };
+                return Some(medium_record(r, t, mat));
+            }
+            if xi < density * (self.sigma_a + self.sigma_s) {
+                return Some(medium_record(r, t, Arc::clone(&self.phase_function)));
+            }
+            // Null collision: keep tracking.
+        }
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bounds
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        let Some(inside) = self.bounds.overlap(r, ray_t) else {
+            return 1.0;
+        };
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        let step_scale = 1.0 / (majorant * r.direction().magnitude());
+        let mut transmittance = 1.0;
+        let mut t = inside.min;
+        loop {
+            t -= (1.0 - random_float()).ln() * step_scale;
+            if t >= inside.max {
+                return transmittance;
+            }
+            let sigma_t = self.density_at(&r.at(t)) * (self.sigma_a + self.sigma_s);
+            transmittance *= 1.0 - sigma_t / majorant;
+        }
+    }
+}
+
+/// A translucent object such as skin, wax, marble or milk, where light enters the surface,
+/// scatters around inside and leaves at another point.
+///
+/// Light refracted into the object through its smooth dielectric surface is traced as a random
+/// walk through a dense scattering medium until it refracts out again. The walk only sees the
+/// boundary, which must be closed but doesn't have to be convex, and ignores any other object
+/// inside it. The material of the boundary is replaced. Light leaves the object diffusely, and
+/// light sources light the points where it leaves directly.
+pub struct Subsurface {
+    walk: Arc<RandomWalk>,
+}
+
+impl Subsurface {
+    /// Creates an object that looks `color` overall, where light of each color channel travels
+    /// on average the distance `mean_free_path` times `scale` between scattering events. Larger
+    /// distances make the object more translucent, and red travelling the furthest gives the
+    /// warm glow of skin. The surface has a refractive index of 1.4.
+    ///
+    /// The color is matched for a surface with no refraction, light reflected back inside by a
+    /// refractive surface makes the object somewhat darker.
+    pub fn new(
+        boundary: Box<dyn Hittable>,
+        color: Vector3<f32>,
+        mean_free_path: Vector3<f32>,
+        scale: f32,
+    ) -> Self {
+        // Find the single scattering albedo giving the color after many scattering events,
+        // following Chiang et al., "Practical and Controllable Subsurface Scattering for
+        // Production Path Tracing" (2016).
+        let albedo = color.map(|a| {
+            let a = a.clamp(0.0, 0.999);
+            1.0 - (a * (-5.09406 + a * (2.61188 - a * 4.31805))).exp()
+        });
+        let sigma_t = mean_free_path.map(|d| 1.0 / (d * scale).max(1e-6));
+        Self {
+            walk: Arc::new(RandomWalk {
+                boundary: Arc::from(boundary),
+                ior: 1.4,
+                sigma_t,
+                sigma_s: albedo.component_mul(&sigma_t),
+                exit_material: Arc::new(Lambertian::new(Vector3::new(1.0, 1.0, 1.0))),
+            }),
+        }
+    }
+
+    /// Use a different refractive index for the surface.
+    pub fn with_ior(mut self, ior: f32) -> Self {
+        Arc::make_mut(&mut self.walk).ior = ior;
+        self
+    }
+}
+
+impl Hittable for Subsurface {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut rec = self.walk.boundary.hit(r, ray_t)?;
+        rec.mat = Arc::clone(&self.walk) as Arc<dyn Material>;
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.walk.boundary.bounding_box()
+    }
+}
+
+/// The surface material of a [`Subsurface`] object, which traces the walk inside and scatters
+/// light out from where it leaves the object.
+#[derive(Clone)]
+struct RandomWalk {
+    boundary: Arc<dyn Hittable>,
+    ior: f32,
+    sigma_t: Vector3<f32>,
+    sigma_s: Vector3<f32>,
+    /// Scatters light out of the object diffusely where the walk leaves it, which lets light
+    /// sources light the exit directly.
+    exit_material: Arc<dyn Material>,
+}
+
+/// Longest walk followed before giving up on the light ever leaving.
+const MAX_WALK_STEPS: u32 = 256;
+
+impl RandomWalk {
+    /// Reflects or refracts the unit direction `direction` at a surface with the unit normal
+    /// `normal` facing it, where `eta` is the refractive index behind over the one in front.
+    /// Returns the new direction and whether it crossed the surface.
+    fn cross_surface(
+        direction: &Vector3<f32>,
+        normal: &Vector3<f32>,
+        eta: f32,
+    ) -> (Vector3<f32>, bool) {
+        let cos_theta = -direction.dot(normal).min(1.0);
+        if random_float() < microfacet::fresnel_dielectric(cos_theta, eta) {
+            return (direction + 2.0 * cos_theta * normal, false);
+        }
+        let sin2_theta_t = (1.0 - cos_theta * cos_theta) / (eta * eta);
+        let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+        (
+            direction / eta + (cos_theta / eta - cos_theta_t) * normal,
+            true,
+        )
+    }
+}
+
+impl Material for RandomWalk {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let unit_direction = r_in.direction().normalize();
+        let mut throughput = Vector3::new(1.0, 1.0, 1.0);
+        let (mut direction, entered) = if rec.front_face {
+            Self::cross_surface(&unit_direction, &rec.normal, self.ior)
+        } else {
+            // Already inside, e.g. a camera placed in the object.
+            (unit_direction, true)
+        };
+        if !entered {
+            return Some(ScatterResult {
+                attenuation: throughput,
+                scattered: Ray::new(rec.p, direction, r_in.time()),
+                exit: None,
+            });
+        }
+
+        let mut p = rec.p;
+        for _ in 0..MAX_WALK_STEPS {
+            if throughput.sum() <= 0.0 {
+                return None;
+            }
+            let ray = Ray::new(p, direction, r_in.time());
+            let exit = self
+                .boundary
+                .hit(&ray, Interval::new(1e-4, f32::INFINITY))?;
+
+            // Sample the distance to the next scattering event in one channel, picked in
+            // proportion to the path throughput so that no channel's weight can blow up, and
+            // weight by the density of having sampled it in any channel.
+            let channel_probabilities = throughput / throughput.sum();
+            let u = random_float();
+            let channel = if u < channel_probabilities.x {
+                0
+            } else if u < channel_probabilities.x + channel_probabilities.y {
+                1
+            } else {
+                2
+            };
+            let distance = -(1.0 - random_float()).ln() / self.sigma_t[channel];
+            let transmittance =
+                |distance: f32| self.sigma_t.map(|sigma_t| (-sigma_t * distance).exp());
+
+            if distance < exit.t {
+                let transmittance = transmittance(distance);
+                let pdf = channel_probabilities.dot(&self.sigma_t.component_mul(&transmittance));
+                throughput
+                    .component_mul_assign(&(self.sigma_s.component_mul(&transmittance) / pdf));
+                p = ray.at(distance);
+                direction = random_unit_vector();
+                continue;
+            }
+
+            let transmittance = transmittance(exit.t);
+            throughput
+                .component_mul_assign(&(transmittance / channel_probabilities.dot(&transmittance)));
+            p = exit.p;
+            // The exit record faces the inside of the object.
+            let (new_direction, left) =
+                Self::cross_surface(&direction, &exit.normal, 1.0 / self.ior);
+            if left {
+                // After many scattering events, the light leaving is close to diffuse. Treat it
+                // as such, so that the exit can be lit directly with a matching material.
+                let mut exit_rec = HitRecord::new(p, exit.t, Arc::clone(&self.exit_material));
+                exit_rec.normal = -exit.normal;
+                exit_rec.front_face = true;
+                let scattered = self.exit_material.scatter(r_in, &exit_rec)?.scattered;
+                return Some(ScatterResult {
+                    attenuation: throughput,
+                    scattered,
+                    exit: Some(exit_rec),
+                });
+            }
+            direction = new_direction;
+        }
+        None
+    }
+}
+
+/// A homogeneous medium filling the whole scene around the camera, such as haze or underwater
+/// murk, set with [`CameraBuilder::atmosphere`](crate::camera::CameraBuilder::atmosphere).
+///
+/// The atmosphere fills the space between surfaces. Rays leaving the scene reach the background
+/// unattenuated, as if the atmosphere ended beyond the furthest geometry.
+pub struct Atmosphere {
+    neg_inv_density: f32,
+    phase_function: Arc<dyn Material>,
+}
+
+impl Atmosphere {
+    /// Creates an isotropic atmosphere of the given color.
+    pub fn new(density: f32, albedo: Vector3<f32>) -> Self {
+        Self::with_phase_function(density, Arc::new(Isotropic::new(albedo)))
+    }
+
+    pub fn with_phase_function(density: f32, phase_function: Arc<dyn Material>) -> Self {
+        Self {
+            neg_inv_density: -1.0 / density,
+            phase_function,
+        }
+    }
+
+    /// Returns a scattering event if the ray scatters in the atmosphere before reaching the
+    /// surface hit at `t_max`.
+    pub fn scatter_before(&self, r: &Ray, t_min: f32, t_max: f32) -> Option<HitRecord> {
+        scatter_record(r, t_min, t_max, self.neg_inv_density, &self.phase_function)
+    }
+
+    /// The fraction of light crossing the distance `distance` through the atmosphere without
+    /// scattering.
+    pub fn transmittance(&self, distance: f32) -> f32 {
+        (distance / self.neg_inv_density).exp()
+    }
+}
+
+/// Samples the distance a ray travels through a uniform medium from `t_min`, returning the
+/// scattering event if it occurs before `t_max`.
+fn scatter_record(
+    r: &Ray,
+    t_min: f32,
+    t_max: f32,
+    neg_inv_density: f32,
+    phase_function: &Arc<dyn Material>,
+) -> Option<HitRecord> {
+    let ray_length = r.direction().magnitude();
+    let distance_inside_boundary = (t_max - t_min) * ray_length;
+    let hit_distance = neg_inv_density * random_float().ln();
+    if hit_distance > distance_inside_boundary {
+        return None;
+    }
+
+    let t = t_min + hit_distance / ray_length;
+    Some(medium_record(r, t, Arc::clone(phase_function)))
+}
+
+fn medium_record(r: &Ray, t: f32, mat: Arc<dyn Material>) -> HitRecord {
+    let mut rec = HitRecord::new(r.at(t), t, mat);
+    // The normal and facing are arbitrary inside a volume.
+    rec.normal = Vector3::new(1.0, 0.0, 0.0);
+    rec.front_face = true;
+    rec
+}
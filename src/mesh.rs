@@ -0,0 +1,196 @@
+use std::sync::Arc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::aabb::Aabb;
+use crate::bvh::BvhNode;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::triangle::{self, VertexAttributes, DEFAULT_UVS};
+
+/// Vertex buffers shared by all triangles of a mesh.
+struct MeshData {
+    positions: Vec<Vector3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    colors: Vec<Vector3<f32>>,
+    indices: Vec<[u32; 3]>,
+    mat: Arc<dyn Material>,
+}
+
+/// A single face of a mesh, referring back into the shared vertex buffers by index.
+struct MeshTriangle {
+    mesh: Arc<MeshData>,
+    face: usize,
+    bbox: Aabb,
+}
+
+impl MeshTriangle {
+    fn vertices(&self) -> [Vector3<f32>; 3] {
+        self.mesh.indices[self.face].map(|i| self.mesh.positions[i as usize])
+    }
+}
+
+impl Hittable for MeshTriangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let vertices = self.vertices();
+        let (t, b1, b2) = triangle::intersect(r, ray_t, &vertices)?;
+
+        let indices = self.mesh.indices[self.face];
+        let attributes = VertexAttributes {
+            normals: (!self.mesh.normals.is_empty())
+                .then(|| indices.map(|i| self.mesh.normals[i as usize])),
+            uvs: if self.mesh.uvs.is_empty() {
+                DEFAULT_UVS
+            } else {
+                indices.map(|i| self.mesh.uvs[i as usize])
+            },
+            colors: (!self.mesh.colors.is_empty())
+                .then(|| indices.map(|i| self.mesh.colors[i as usize])),
+        };
+
+        Some(triangle::hit_record(
+            r,
+            t,
+            (b1, b2),
+            &vertices,
+            &attributes,
+            Arc::clone(&self.mesh.mat),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// An indexed triangle mesh. The faces are organized in their own BVH, so a mesh can be added to a
+/// scene as a single object.
+pub struct TriangleMesh {
+    triangle_count: usize,
+    bvh: BvhNode,
+}
+
+impl TriangleMesh {
+    pub fn triangle_count(&self) -> usize {
+        self.triangle_count
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.bvh.hit(r, ray_t)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        self.bvh.transmittance(r, ray_t)
+    }
+}
+
+pub struct TriangleMeshBuilder {
+    positions: Vec<Vector3<f32>>,
+    normals: Vec<Vector3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    colors: Vec<Vector3<f32>>,
+    indices: Vec<[u32; 3]>,
+}
+
+impl TriangleMeshBuilder {
+    /// Create a new builder from vertex positions and faces indexing into them, in
+    /// counter-clockwise order.
+    pub fn new(positions: Vec<Vector3<f32>>, indices: Vec<[u32; 3]>) -> Self {
+        Self {
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            colors: Vec::new(),
+            indices,
+        }
+    }
+
+    /// Per-vertex normals, one for each position.
+    pub fn normals(mut self, normals: Vec<Vector3<f32>>) -> Self {
+        self.normals = normals;
+        self
+    }
+
+    /// Per-vertex texture coordinates, one for each position.
+    pub fn uvs(mut self, uvs: Vec<Vector2<f32>>) -> Self {
+        self.uvs = uvs;
+        self
+    }
+
+    /// Per-vertex colors in linear space, one for each position. They tint the mesh material.
+    pub fn colors(mut self, colors: Vec<Vector3<f32>>) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Build the mesh and its BVH.
+    ///
+    /// Panics if a face refers to a vertex that does not exist, or if normals, texture coordinates
+    /// or colors are given but don't match the number of positions.
+    pub fn build(self, mat: Arc<dyn Material>) -> TriangleMesh {
+        let vertex_count = self.positions.len();
+        assert!(
+            self.normals.is_empty() || self.normals.len() == vertex_count,
+            "expected {vertex_count} normals, got {}",
+            self.normals.len()
+        );
+        assert!(
+            self.uvs.is_empty() || self.uvs.len() == vertex_count,
+            "expected {vertex_count} texture coordinates, got {}",
+            self.uvs.len()
+        );
+        assert!(
+            self.colors.is_empty() || self.colors.len() == vertex_count,
+            "expected {vertex_count} colors, got {}",
+            self.colors.len()
+        );
+        assert!(
+            self.indices
+                .iter()
+                .flatten()
+                .all(|&i| (i as usize) < vertex_count),
+            "face index out of range for {vertex_count} vertices"
+        );
+
+        let mesh = Arc::new(MeshData {
+            positions: self.positions,
+            // Zero normals stay zero rather than NaN, leaving the shading to the other vertices
+            // or, without any, to the geometric normal.
+            normals: self
+                .normals
+                .into_iter()
+                .map(|n| n.try_normalize(0.0).unwrap_or_else(Vector3::zeros))
+                .collect(),
+            uvs: self.uvs,
+            colors: self.colors,
+            indices: self.indices,
+            mat,
+        });
+
+        let triangles: Vec<Box<dyn Hittable>> = (0..mesh.indices.len())
+            .map(|face| {
+                let [a, b, c] = mesh.indices[face].map(|i| mesh.positions[i as usize]);
+                let bbox = Aabb::enclosing(&Aabb::from_points(a, b), &Aabb::from_points(a, c));
+                Box::new(MeshTriangle {
+                    mesh: Arc::clone(&mesh),
+                    face,
+                    bbox,
+                }) as Box<dyn Hittable>
+            })
+            .collect();
+
+        TriangleMesh {
+            triangle_count: triangles.len(),
+            bvh: BvhNode::new(triangles),
+        }
+    }
+}
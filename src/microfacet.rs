@@ -0,0 +1,180 @@
+//! The GGX (Trowbridge–Reitz) microfacet distribution, with Smith masking-shadowing, sampling of
+//! the visible normals and Fresnel reflectance.
+//!
+//! Directions are given in a local shading frame with the macro surface normal along z, and
+//! point away from the surface. The roughness is given as `alpha` values along the local x and y
+//! axes, which are equal for isotropic surfaces.
+
+use std::f32::consts::PI;
+
+use nalgebra::{Complex, Vector3};
+
+/// Smallest alpha used, since a perfectly smooth distribution is a Dirac delta.
+pub const MIN_ALPHA: f32 = 1e-3;
+
+/// Converts the perceptual roughness in [0, 1] used by artists to the GGX alpha parameter.
+pub fn roughness_to_alpha(roughness: f32) -> f32 {
+    (roughness * roughness).max(MIN_ALPHA)
+}
+
+/// The distribution of microfacet normals `m`.
+pub fn d(m: &Vector3<f32>, alpha_x: f32, alpha_y: f32) -> f32 {
+    if m.z <= 0.0 {
+        return 0.0;
+    }
+    let e = (m.x / alpha_x).powi(2) + (m.y / alpha_y).powi(2) + m.z * m.z;
+    1.0 / (PI * alpha_x * alpha_y * e * e)
+}
+
+/// The Smith auxiliary function, from which the masking terms are derived.
+fn lambda(w: &Vector3<f32>, alpha_x: f32, alpha_y: f32) -> f32 {
+    let cos2 = w.z * w.z;
+    if cos2 <= 0.0 {
+        return f32::INFINITY;
+    }
+    let tan2_alpha2 = ((alpha_x * w.x).powi(2) + (alpha_y * w.y).powi(2)) / cos2;
+    0.5 * ((1.0 + tan2_alpha2).sqrt() - 1.0)
+}
+
+/// The fraction of microfacets visible from the direction `w`.
+pub fn g1(w: &Vector3<f32>, alpha_x: f32, alpha_y: f32) -> f32 {
+    1.0 / (1.0 + lambda(w, alpha_x, alpha_y))
+}
+
+/// The height-correlated fraction of microfacets visible from both `wo` and `wi`.
+pub fn g2(wo: &Vector3<f32>, wi: &Vector3<f32>, alpha_x: f32, alpha_y: f32) -> f32 {
+    1.0 / (1.0 + lambda(wo, alpha_x, alpha_y) + lambda(wi, alpha_x, alpha_y))
+}
+
+/// Samples a microfacet normal visible from `wo` from the uniform random numbers `u1` and `u2`,
+/// following Heitz, "Sampling the GGX Distribution of Visible Normals" (2018).
+pub fn sample_visible_normal(
+    wo: &Vector3<f32>,
+    alpha_x: f32,
+    alpha_y: f32,
+    u1: f32,
+    u2: f32,
+) -> Vector3<f32> {
+    // Stretch the view direction onto the hemisphere configuration with unit roughness.
+    let vh = Vector3::new(alpha_x * wo.x, alpha_y * wo.y, wo.z).normalize();
+
+    // Build an orthonormal basis around it.
+    let lensq = vh.x * vh.x + vh.y * vh.y;
+    let t1 = if lensq > 0.0 {
+        Vector3::new(-vh.y, vh.x, 0.0) / lensq.sqrt()
+    } else {
+        Vector3::new(1.0, 0.0, 0.0)
+    };
+    let t2 = vh.cross(&t1);
+
+    // Sample the projected area of the visible hemisphere.
+    let r = u1.sqrt();
+    let phi = 2.0 * PI * u2;
+    let p1 = r * phi.cos();
+    let s = 0.5 * (1.0 + vh.z);
+    let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+    let nh = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * vh;
+
+    // Unstretch back to the actual roughness.
+    Vector3::new(alpha_x * nh.x, alpha_y * nh.y, nh.z.max(0.0)).normalize()
+}
+
+/// The probability density of sampling the reflected direction `wi` by reflecting `wo` about a
+/// normal from [`sample_visible_normal`].
+pub fn reflection_pdf(wo: &Vector3<f32>, wi: &Vector3<f32>, alpha_x: f32, alpha_y: f32) -> f32 {
+    if wo.z <= 0.0 || wi.z <= 0.0 {
+        return 0.0;
+    }
+    let m = (wo + wi).normalize();
+    g1(wo, alpha_x, alpha_y) * d(&m, alpha_x, alpha_y) / (4.0 * wo.z)
+}
+
+/// Schlick's approximation of the Fresnel reflectance, from the reflectance `f0` at normal
+/// incidence.
+pub fn fresnel_schlick(f0: &Vector3<f32>, cos_theta: f32) -> Vector3<f32> {
+    let weight = (1.0 - cos_theta.clamp(0.0, 1.0)).powi(5);
+    f0 + (Vector3::new(1.0, 1.0, 1.0) - f0) * weight
+}
+
+/// The exact Fresnel reflectance of unpolarized light on a dielectric boundary, where `eta` is
+/// the refractive index on the far side over the one on the near side. Returns 1 for total
+/// internal reflection.
+pub fn fresnel_dielectric(cos_theta_i: f32, eta: f32) -> f32 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_t = (1.0 - cos_theta_i * cos_theta_i) / (eta * eta);
+    if sin2_theta_t >= 1.0 {
+        return 1.0;
+    }
+    let cos_theta_t = (1.0 - sin2_theta_t).sqrt();
+
+    let r_parallel = (eta * cos_theta_i - cos_theta_t) / (eta * cos_theta_i + cos_theta_t);
+    let r_perpendicular = (cos_theta_i - eta * cos_theta_t) / (cos_theta_i + eta * cos_theta_t);
+    0.5 * (r_parallel * r_parallel + r_perpendicular * r_perpendicular)
+}
+
+/// The exact Fresnel reflectance of unpolarized light on a conductor, with the complex index of
+/// refraction `eta + i k` given for each color channel relative to the outside medium.
+pub fn fresnel_conductor(eta: &Vector3<f32>, k: &Vector3<f32>, cos_theta: f32) -> Vector3<f32> {
+    let cos_theta = cos_theta.clamp(0.0, 1.0);
+    let cos2 = cos_theta * cos_theta;
+    let sin2 = 1.0 - cos2;
+    Vector3::from_fn(|i, _| {
+        let (eta, k) = (eta[i], k[i]);
+        let t0 = eta * eta - k * k - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta * eta * k * k).sqrt();
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+
+        let t1 = a2_plus_b2 + cos2;
+        let t2 = 2.0 * cos_theta * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rp + rs)
+    })
+}
+
+/// The reflectance of unpolarized light of a single wavelength on a thin film over a substrate,
+/// where light reflected on both sides of the film interferes. The film has the index `eta_film`
+/// and the substrate the complex index `eta_base + i k_base`, both relative to the incident
+/// medium, and `wavelength` is measured in that medium in the same unit as `thickness`.
+///
+/// The multiple reflections inside the film are summed with Airy's formula for each
+/// polarization.
+pub fn fresnel_thin_film(
+    cos_theta_i: f32,
+    eta_film: f32,
+    thickness: f32,
+    eta_base: f32,
+    k_base: f32,
+    wavelength: f32,
+) -> f32 {
+    let cos_theta_i = cos_theta_i.clamp(0.0, 1.0);
+    let sin2_theta_i = 1.0 - cos_theta_i * cos_theta_i;
+
+    // Snell's law with complex indices, which also covers total internal reflection in the film
+    // and absorbing substrates.
+    let one = Complex::new(1.0, 0.0);
+    let n2 = Complex::new(eta_film, 0.0);
+    let n3 = Complex::new(eta_base, k_base);
+    let cos_theta = |n: Complex<f32>| (one - sin2_theta_i / (n * n)).sqrt();
+    let (c1, c2, c3) = (Complex::new(cos_theta_i, 0.0), cos_theta(n2), cos_theta(n3));
+
+    // Phase difference between consecutive reflections out of the film.
+    let phase = (Complex::i() * (4.0 * PI * thickness / wavelength) * n2 * c2).exp();
+    let airy = |r12: Complex<f32>, r23: Complex<f32>| {
+        ((r12 + r23 * phase) / (one + r12 * r23 * phase)).norm_sqr()
+    };
+
+    let rs = airy(
+        (c1 - n2 * c2) / (c1 + n2 * c2),
+        (n2 * c2 - n3 * c3) / (n2 * c2 + n3 * c3),
+    );
+    let rp = airy(
+        (n2 * c1 - c2) / (n2 * c1 + c2),
+        (n3 * c2 - n2 * c3) / (n3 * c2 + n2 * c3),
+    );
+    (0.5 * (rs + rp)).min(1.0)
+}
@@ -0,0 +1,544 @@
+//! Loader for Wavefront OBJ meshes and their MTL material libraries.
+//!
+//! Supported statements are vertex positions (`v`), texture coordinates (`vt`), normals (`vn`),
+//! faces (`f`), groups and objects (`g`, `o`) and materials (`mtllib`, `usemtl`). Faces with more
+//! than three vertices are fan triangulated, so they are expected to be convex. Anything else,
+//! like smoothing groups or free-form geometry, is ignored.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::hittable::HittableList;
+use crate::material::{AlphaMode, Conductor, Cutout, Dielectric, Lambertian, Material, Metal};
+use crate::mesh::{TriangleMesh, TriangleMeshBuilder};
+use crate::texture::{ImageTexture, SolidColor, Texture, TintedTexture};
+
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// A statement in an OBJ or MTL file is malformed.
+    Parse {
+        path: PathBuf,
+        line: usize,
+        message: String,
+    },
+    /// A texture referenced by a material could not be loaded.
+    Texture {
+        path: PathBuf,
+        source: image::ImageError,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ObjError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            ObjError::Parse {
+                path,
+                line,
+                message,
+            } => write!(f, "{}:{line}: {message}", path.display()),
+            ObjError::Texture { path, source } => {
+                write!(f, "failed to load texture {}: {source}", path.display())
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { source, .. } => Some(source),
+            ObjError::Parse { .. } => None,
+            ObjError::Texture { source, .. } => Some(source),
+        }
+    }
+}
+
+/// The faces of one group of an OBJ file sharing a single material.
+pub struct ObjGroup {
+    pub name: String,
+    pub material: Option<String>,
+    pub mesh: TriangleMesh,
+}
+
+pub struct ObjModel {
+    pub groups: Vec<ObjGroup>,
+}
+
+impl ObjModel {
+    pub fn into_hittable_list(self) -> HittableList {
+        let mut list = HittableList::new();
+        for group in self.groups {
+            list.add(Box::new(group.mesh));
+        }
+        list
+    }
+}
+
+/// Load an OBJ file, along with any material libraries it references. Faces without a material
+/// are assigned `default_mat`.
+pub fn load(path: impl AsRef<Path>, default_mat: Arc<dyn Material>) -> Result<ObjModel, ObjError> {
+    let path = path.as_ref();
+    let source = read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut positions: Vec<Vector3<f32>> = Vec::new();
+    let mut uvs: Vec<Vector2<f32>> = Vec::new();
+    let mut normals: Vec<Vector3<f32>> = Vec::new();
+    let mut materials: HashMap<String, Arc<dyn Material>> = HashMap::new();
+
+    let mut groups: Vec<GroupBuilder> = Vec::new();
+    let mut group_lookup: HashMap<(String, Option<String>), usize> = HashMap::new();
+    let mut group_name = String::from("default");
+    let mut material_name: Option<String> = None;
+
+    for (index, line) in source.lines().enumerate() {
+        let line_number = index + 1;
+        let parser = LineParser::new(path, line_number, line);
+        let Some(keyword) = parser.keyword() else {
+            continue;
+        };
+
+        match keyword {
+            "v" => positions.push(parser.vector3()?),
+            "vt" => uvs.push(parser.vector2()?),
+            "vn" => normals.push(parser.vector3()?),
+            "g" | "o" => {
+                let name = parser.rest();
+                group_name = if name.is_empty() {
+                    String::from("default")
+                } else {
+                    name.to_string()
+                };
+            }
+            "mtllib" => {
+                for library in parser.args() {
+                    materials.extend(load_mtl(&dir.join(library))?);
+                }
+            }
+            "usemtl" => {
+                let name = parser.rest();
+                if !materials.contains_key(name) {
+                    return Err(parser.error(format!("undefined material '{name}'")));
+                }
+                material_name = Some(name.to_string());
+            }
+            "f" => {
+                let face = parser
+                    .args()
+                    .map(|arg| parser.face_vertex(arg, positions.len(), uvs.len(), normals.len()))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if face.len() < 3 {
+                    return Err(parser.error(format!(
+                        "face needs at least 3 vertices, got {}",
+                        face.len()
+                    )));
+                }
+
+                let key = (group_name.clone(), material_name.clone());
+                let group_index = *group_lookup.entry(key).or_insert_with(|| {
+                    groups.push(GroupBuilder::new(&group_name, material_name.as_deref()));
+                    groups.len() - 1
+                });
+                let group = &mut groups[group_index];
+
+                let indices: Vec<u32> = face
+                    .into_iter()
+                    .map(|vertex| group.vertex(vertex, &positions, &uvs, &normals))
+                    .collect();
+                // Triangulate the polygon as a fan around its first vertex.
+                for i in 1..indices.len() - 1 {
+                    group.indices.push([indices[0], indices[i], indices[i + 1]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let groups = groups
+        .into_iter()
+        .map(|group| {
+            let mat = group
+                .material
+                .as_ref()
+                .map_or_else(|| Arc::clone(&default_mat), |m| Arc::clone(&materials[m]));
+            group.build(mat)
+        })
+        .collect();
+
+    Ok(ObjModel { groups })
+}
+
+/// Load an MTL material library, mapping each material onto the closest matching material type:
+///
+/// - materials with a `conductor` statement naming a metal known to [`Conductor::named`], such
+///   as `conductor Au`, become that [`Conductor`], with the shininess `Ns` mapped to roughness,
+/// - transparent materials (`d` below 1, or `illum` 4, 6, 7 or 9) become a [`Dielectric`] with
+///   refraction index `Ni`,
+/// - materials whose specular color `Ks` outweighs their diffuse color `Kd` become a [`Metal`],
+///   with the shininess `Ns` mapped to fuzz,
+/// - everything else becomes a [`Lambertian`] using `Kd`, multiplying the texture `map_Kd` if
+///   there is one.
+///
+/// An alpha texture `map_d` cuts out the parts of the material where it is below one half, using
+/// the alpha channel of the image if it has one.
+pub fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let source = read_to_string(path)?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+
+    let mut entries: Vec<(String, MtlEntry)> = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let parser = LineParser::new(path, index + 1, line);
+        let Some(keyword) = parser.keyword() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            entries.push((parser.rest().to_string(), MtlEntry::default()));
+            continue;
+        }
+        let Some((_, entry)) = entries.last_mut() else {
+            if matches!(
+                keyword,
+                "Kd" | "Ks" | "Ns" | "Ni" | "d" | "Tr" | "illum" | "map_Kd" | "map_d" | "conductor"
+            ) {
+                return Err(parser.error(format!("'{keyword}' before any 'newmtl'")));
+            }
+            continue;
+        };
+
+        match keyword {
+            "Kd" => entry.kd = parser.vector3()?,
+            "Ks" => entry.ks = parser.vector3()?,
+            "Ns" => entry.ns = parser.float()?,
+            "Ni" => entry.ni = parser.float()?,
+            "d" => entry.d = parser.float()?,
+            "Tr" => entry.d = 1.0 - parser.float()?,
+            "illum" => entry.illum = parser.float()? as u32,
+            "conductor" => {
+                let name = parser.rest();
+                if Conductor::named(name, 0.0).is_none() {
+                    return Err(parser.error(format!("unknown conductor '{name}'")));
+                }
+                entry.conductor = Some(name.to_string());
+            }
+            "map_Kd" => entry.map_kd = Some(Arc::new(load_map(&parser, dir, true)?)),
+            "map_d" => {
+                let texture = load_map(&parser, dir, false)?;
+                entry.map_d = Some(Arc::new(if texture.has_alpha() {
+                    texture.alpha_channel()
+                } else {
+                    texture
+                }));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| (name, entry.into_material()))
+        .collect())
+}
+
+/// Loads the texture named at the end of a texture map statement, relative to the directory
+/// `dir` of the material library.
+fn load_map(parser: &LineParser, dir: &Path, srgb: bool) -> Result<ImageTexture, ObjError> {
+    // Texture options precede the file name, which is always the last argument.
+    let Some(file) = parser.args().last() else {
+        return Err(parser.error("missing texture file name".to_string()));
+    };
+    let texture_path = dir.join(file);
+    let texture = if srgb {
+        ImageTexture::open(&texture_path)
+    } else {
+        ImageTexture::open_linear(&texture_path)
+    };
+    texture.map_err(|source| ObjError::Texture {
+        path: texture_path,
+        source,
+    })
+}
+
+fn read_to_string(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|source| ObjError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+struct MtlEntry {
+    kd: Vector3<f32>,
+    ks: Vector3<f32>,
+    ns: f32,
+    ni: f32,
+    d: f32,
+    illum: u32,
+    map_kd: Option<Arc<ImageTexture>>,
+    map_d: Option<Arc<ImageTexture>>,
+    conductor: Option<String>,
+}
+
+impl Default for MtlEntry {
+    fn default() -> Self {
+        Self {
+            kd: Vector3::new(0.8, 0.8, 0.8),
+            ks: Vector3::new(0.0, 0.0, 0.0),
+            ns: 0.0,
+            ni: 1.5,
+            d: 1.0,
+            illum: 2,
+            map_kd: None,
+            map_d: None,
+            conductor: None,
+        }
+    }
+}
+
+impl MtlEntry {
+    fn into_material(mut self) -> Arc<dyn Material> {
+        match self.map_d.take() {
+            Some(alpha) => Arc::new(Cutout::new(
+                self.into_opaque_material(),
+                alpha,
+                AlphaMode::Cutoff(0.5),
+            )),
+            None => self.into_opaque_material(),
+        }
+    }
+
+    fn into_opaque_material(self) -> Arc<dyn Material> {
+        // The Phong exponent maps to a Beckmann roughness, whose square root is close to the
+        // perceptual GGX roughness.
+        let alpha = (2.0 / (self.ns + 2.0)).sqrt();
+        if let Some(conductor) = self
+            .conductor
+            .and_then(|name| Conductor::named(&name, alpha.sqrt()))
+        {
+            return Arc::new(conductor);
+        }
+        if self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9) {
+            return Arc::new(Dielectric::new(self.ni));
+        }
+        if self.map_kd.is_none() && self.ks.max() > self.kd.max() {
+            return Arc::new(Metal::new(self.ks, alpha));
+        }
+        let tex: Arc<dyn Texture> = match self.map_kd {
+            Some(map) if self.kd == Vector3::repeat(1.0) => map,
+            Some(map) => Arc::new(TintedTexture::new(map, self.kd)),
+            None => Arc::new(SolidColor::new(self.kd)),
+        };
+        Arc::new(Lambertian::from_texture(tex))
+    }
+}
+
+/// Indices of the position, texture coordinate and normal of one face vertex.
+type FaceVertex = (usize, Option<usize>, Option<usize>);
+
+/// Collects the faces of one group, re-indexing the OBJ attributes into a single vertex buffer.
+struct GroupBuilder {
+    name: String,
+    material: Option<String>,
+    positions: Vec<Vector3<f32>>,
+    uvs: Vec<Vector2<f32>>,
+    normals: Vec<Vector3<f32>>,
+    has_uvs: bool,
+    missing_normals: bool,
+    indices: Vec<[u32; 3]>,
+    vertex_lookup: HashMap<FaceVertex, u32>,
+}
+
+impl GroupBuilder {
+    fn new(name: &str, material: Option<&str>) -> Self {
+        Self {
+            name: name.to_string(),
+            material: material.map(str::to_string),
+            positions: Vec::new(),
+            uvs: Vec::new(),
+            normals: Vec::new(),
+            has_uvs: false,
+            missing_normals: false,
+            indices: Vec::new(),
+            vertex_lookup: HashMap::new(),
+        }
+    }
+
+    fn vertex(
+        &mut self,
+        vertex: FaceVertex,
+        positions: &[Vector3<f32>],
+        uvs: &[Vector2<f32>],
+        normals: &[Vector3<f32>],
+    ) -> u32 {
+        if let Some(&index) = self.vertex_lookup.get(&vertex) {
+            return index;
+        }
+
+        let (p, vt, vn) = vertex;
+        self.positions.push(positions[p]);
+        self.has_uvs |= vt.is_some();
+        self.uvs.push(vt.map_or(Vector2::new(0.0, 0.0), |i| uvs[i]));
+        self.missing_normals |= vn.is_none();
+        self.normals
+            .push(vn.map_or(Vector3::new(0.0, 0.0, 0.0), |i| normals[i]));
+
+        let index = self.positions.len() as u32 - 1;
+        self.vertex_lookup.insert(vertex, index);
+        index
+    }
+
+    /// Build the group's mesh. Normals are only used if every vertex has one, otherwise the group
+    /// is flat shaded.
+    fn build(self, mat: Arc<dyn Material>) -> ObjGroup {
+        let mut builder = TriangleMeshBuilder::new(self.positions, self.indices);
+        if !self.missing_normals {
+            builder = builder.normals(self.normals);
+        }
+        if self.has_uvs {
+            builder = builder.uvs(self.uvs);
+        }
+        ObjGroup {
+            name: self.name,
+            material: self.material,
+            mesh: builder.build(mat),
+        }
+    }
+}
+
+/// Splits a single line of an OBJ or MTL file into its keyword and arguments.
+struct LineParser<'a> {
+    path: &'a Path,
+    line: usize,
+    content: &'a str,
+}
+
+impl<'a> LineParser<'a> {
+    fn new(path: &'a Path, line: usize, content: &'a str) -> Self {
+        // Everything after a '#' is a comment.
+        let content = content.split('#').next().unwrap_or("").trim();
+        Self {
+            path,
+            line,
+            content,
+        }
+    }
+
+    fn keyword(&self) -> Option<&'a str> {
+        self.content.split_whitespace().next()
+    }
+
+    fn args(&self) -> impl Iterator<Item = &'a str> {
+        self.content.split_whitespace().skip(1)
+    }
+
+    /// The arguments as a single string, for names that may contain spaces.
+    fn rest(&self) -> &'a str {
+        self.content
+            .split_once(char::is_whitespace)
+            .map_or("", |(_, rest)| rest.trim())
+    }
+
+    fn error(&self, message: String) -> ObjError {
+        ObjError::Parse {
+            path: self.path.to_path_buf(),
+            line: self.line,
+            message,
+        }
+    }
+
+    fn parse_float(&self, arg: &str) -> Result<f32, ObjError> {
+        arg.parse()
+            .map_err(|_| self.error(format!("invalid number '{arg}'")))
+    }
+
+    /// Parse the first `N` arguments as floats. Any further arguments, like the optional `w`
+    /// component of vertex positions, are ignored.
+    fn floats<const N: usize>(&self) -> Result<[f32; N], ObjError> {
+        let mut values = [0.0; N];
+        let mut args = self.args();
+        for (i, value) in values.iter_mut().enumerate() {
+            let arg = args
+                .next()
+                .ok_or_else(|| self.error(format!("expected {N} numbers, got {i}")))?;
+            *value = self.parse_float(arg)?;
+        }
+        Ok(values)
+    }
+
+    fn float(&self) -> Result<f32, ObjError> {
+        let [x] = self.floats()?;
+        Ok(x)
+    }
+
+    fn vector2(&self) -> Result<Vector2<f32>, ObjError> {
+        // The v coordinate of texture coordinates is optional.
+        let mut args = self.args();
+        let u = match args.next() {
+            Some(arg) => self.parse_float(arg)?,
+            None => return Err(self.error("expected texture coordinates".to_string())),
+        };
+        let v = match args.next() {
+            Some(arg) => self.parse_float(arg)?,
+            None => 0.0,
+        };
+        Ok(Vector2::new(u, v))
+    }
+
+    fn vector3(&self) -> Result<Vector3<f32>, ObjError> {
+        let [x, y, z] = self.floats()?;
+        Ok(Vector3::new(x, y, z))
+    }
+
+    /// Parse a face vertex of the form `v`, `v/vt`, `v//vn` or `v/vt/vn` into zero-based indices.
+    fn face_vertex(
+        &self,
+        arg: &str,
+        position_count: usize,
+        uv_count: usize,
+        normal_count: usize,
+    ) -> Result<FaceVertex, ObjError> {
+        let mut parts = arg.split('/');
+        let position = match parts.next() {
+            Some(part) => self.index(part, position_count)?,
+            None => return Err(self.error(format!("invalid face vertex '{arg}'"))),
+        };
+        let uv = match parts.next() {
+            Some(part) if !part.is_empty() => Some(self.index(part, uv_count)?),
+            _ => None,
+        };
+        let normal = match parts.next() {
+            Some(part) if !part.is_empty() => Some(self.index(part, normal_count)?),
+            _ => None,
+        };
+        Ok((position, uv, normal))
+    }
+
+    /// Resolve a one-based OBJ index, where negative values count back from the most recently
+    /// defined element.
+    fn index(&self, part: &str, count: usize) -> Result<usize, ObjError> {
+        let index: i64 = part
+            .parse()
+            .map_err(|_| self.error(format!("invalid index '{part}'")))?;
+        let resolved = match index {
+            i if i > 0 => i - 1,
+            i if i < 0 => count as i64 + i,
+            _ => return Err(self.error("indices start at 1, got 0".to_string())),
+        };
+        if resolved < 0 || resolved >= count as i64 {
+            return Err(self.error(format!(
+                "index {index} out of range, {count} elements defined"
+            )));
+        }
+        Ok(resolved as usize)
+    }
+}
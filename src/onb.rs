@@ -0,0 +1,62 @@
+use nalgebra::Vector3;
+
+/// An orthonormal basis, with `w` along a given direction and `u`, `v` spanning the plane
+/// perpendicular to it.
+#[derive(Clone, Copy, Debug)]
+pub struct Onb {
+    axis: [Vector3<f32>; 3],
+}
+
+impl Onb {
+    pub fn new(n: &Vector3<f32>) -> Self {
+        let w = n.normalize();
+        let a = if w.x.abs() > 0.9 {
+            Vector3::new(0.0, 1.0, 0.0)
+        } else {
+            Vector3::new(1.0, 0.0, 0.0)
+        };
+        let v = w.cross(&a).normalize();
+        let u = w.cross(&v);
+        Self { axis: [u, v, w] }
+    }
+
+    /// Creates a basis with `w` along `n` and `u` along the part of `tangent` perpendicular to
+    /// `n`, falling back to an arbitrary `u` if the tangent is zero or parallel to `n`.
+    pub fn from_tangent(n: &Vector3<f32>, tangent: &Vector3<f32>) -> Self {
+        let w = n.normalize();
+        let u = tangent - tangent.dot(&w) * w;
+        if u.magnitude_squared() < 1e-12 {
+            return Self::new(n);
+        }
+        let u = u.normalize();
+        Self {
+            axis: [u, w.cross(&u), w],
+        }
+    }
+
+    pub fn u(&self) -> Vector3<f32> {
+        self.axis[0]
+    }
+
+    pub fn v(&self) -> Vector3<f32> {
+        self.axis[1]
+    }
+
+    pub fn w(&self) -> Vector3<f32> {
+        self.axis[2]
+    }
+
+    /// Transform from basis coordinates to local space.
+    pub fn transform(&self, v: &Vector3<f32>) -> Vector3<f32> {
+        v.x * self.axis[0] + v.y * self.axis[1] + v.z * self.axis[2]
+    }
+
+    /// Transform from local space to basis coordinates.
+    pub fn to_basis(&self, v: &Vector3<f32>) -> Vector3<f32> {
+        Vector3::new(
+            v.dot(&self.axis[0]),
+            v.dot(&self.axis[1]),
+            v.dot(&self.axis[2]),
+        )
+    }
+}
@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::quad::hit_plane;
+use crate::ray::Ray;
+
+/// An infinite plane through `point`, facing along `normal`.
+///
+/// The texture coordinates repeat every unit of distance along the plane. Since its bounding box
+/// is unbounded, a plane is best kept out of a BVH and added next to it instead.
+pub struct Plane {
+    point: Vector3<f32>,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    d: f32,
+}
+
+impl Plane {
+    pub fn new(point: Vector3<f32>, normal: Vector3<f32>, mat: Arc<dyn Material>) -> Self {
+        let onb = Onb::new(&normal);
+
+        // The plane only has a finite extent along an axis it is perpendicular to.
+        let n = onb.w();
+        let extent = |axis: usize| {
+            if (0..3).all(|other| other == axis || n[other] == 0.0) {
+                Interval::new(point[axis], point[axis])
+            } else {
+                Interval::UNIVERSE
+            }
+        };
+
+        Self {
+            point,
+            onb,
+            mat,
+            bbox: Aabb::new(extent(0), extent(1), extent(2)),
+            d: n.dot(&point),
+        }
+    }
+}
+
+impl Hittable for Plane {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let t = hit_plane(r, ray_t, &self.onb.w(), self.d)?;
+
+        let intersection = r.at(t);
+        let local = self.onb.to_basis(&(intersection - self.point));
+
+        let mut rec = HitRecord::new(intersection, t, Arc::clone(&self.mat));
+        rec.u = local.x.rem_euclid(1.0);
+        rec.v = local.y.rem_euclid(1.0);
+        rec.tangent = self.onb.u();
+        rec.set_face_normal(r, self.onb.w());
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
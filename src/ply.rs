@@ -0,0 +1,412 @@
+//! Loader for Stanford PLY meshes, in ASCII as well as little- and big-endian binary encoding.
+//!
+//! Only the `vertex` and `face` elements are used. Vertices need `x`, `y` and `z` properties, and
+//! may have normals (`nx`, `ny`, `nz`), colors (`red`, `green`, `blue`) and texture coordinates
+//! (`u`/`v`, `s`/`t` or `texture_u`/`texture_v`). Faces are read from a `vertex_indices` (or
+//! `vertex_index`) list and fan triangulated. Any other element is skipped.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::material::Material;
+use crate::mesh::{TriangleMesh, TriangleMeshBuilder};
+
+#[derive(Debug)]
+pub enum PlyError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The header or the data of the file is malformed.
+    Parse { path: PathBuf, message: String },
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlyError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            PlyError::Parse { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PlyError::Io { source, .. } => Some(source),
+            PlyError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Load a PLY file as a triangle mesh with the material `mat`. Vertex colors, if present, tint the
+/// material. Integer colors are decoded from sRGB, with negative values of signed types clamped
+/// to zero, while floating point colors are used as they are.
+pub fn load(path: impl AsRef<Path>, mat: Arc<dyn Material>) -> Result<TriangleMesh, PlyError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|source| PlyError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    parse(&data)
+        .map(|builder| builder.build(mat))
+        .map_err(|message| PlyError::Parse {
+            path: path.to_path_buf(),
+            message,
+        })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "char" | "int8" => Ok(ScalarType::I8),
+            "uchar" | "uint8" => Ok(ScalarType::U8),
+            "short" | "int16" => Ok(ScalarType::I16),
+            "ushort" | "uint16" => Ok(ScalarType::U16),
+            "int" | "int32" => Ok(ScalarType::I32),
+            "uint" | "uint32" => Ok(ScalarType::U32),
+            "float" | "float32" => Ok(ScalarType::F32),
+            "double" | "float64" => Ok(ScalarType::F64),
+            _ => Err(format!("unknown property type '{name}'")),
+        }
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+
+    /// The value that integer colors of this type are normalized by, or `None` for floating
+    /// point types.
+    fn color_scale(self) -> Option<f64> {
+        match self {
+            ScalarType::I8 => Some(i8::MAX as f64),
+            ScalarType::U8 => Some(u8::MAX as f64),
+            ScalarType::I16 => Some(i16::MAX as f64),
+            ScalarType::U16 => Some(u16::MAX as f64),
+            ScalarType::I32 => Some(i32::MAX as f64),
+            ScalarType::U32 => Some(u32::MAX as f64),
+            ScalarType::F32 | ScalarType::F64 => None,
+        }
+    }
+}
+
+enum PropertyKind {
+    Scalar(ScalarType),
+    List { count: ScalarType, item: ScalarType },
+}
+
+struct Property {
+    name: String,
+    kind: PropertyKind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    fn property_index(&self, names: &[&str]) -> Option<usize> {
+        self.properties
+            .iter()
+            .position(|p| names.contains(&p.name.as_str()))
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    /// Offset of the first byte after the header.
+    body_start: usize,
+}
+
+fn parse_header(data: &[u8]) -> Result<Header, String> {
+    const END_HEADER: &[u8] = b"end_header";
+    let end = data
+        .windows(END_HEADER.len())
+        .position(|window| window == END_HEADER)
+        .ok_or("missing 'end_header'")?;
+    // The body starts after the line break following 'end_header'.
+    let body_start = data[end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map_or(data.len(), |i| end + i + 1);
+
+    let text = std::str::from_utf8(&data[..end]).map_err(|_| "header is not valid text")?;
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err("not a PLY file".to_string());
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            [] | ["comment", ..] | ["obj_info", ..] => {}
+            ["format", name, _version] => {
+                format = Some(match *name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(format!("unknown format '{name}'")),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| format!("invalid element count '{count}'"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or("property before any element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::List {
+                        count: ScalarType::parse(count)?,
+                        item: ScalarType::parse(item)?,
+                    },
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or("property before any element")?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: PropertyKind::Scalar(ScalarType::parse(ty)?),
+                });
+            }
+            _ => return Err(format!("invalid header line '{line}'")),
+        }
+    }
+
+    Ok(Header {
+        format: format.ok_or("missing 'format'")?,
+        elements,
+        body_start,
+    })
+}
+
+/// Reads the values of the body one by one, whatever its encoding.
+enum BodyReader<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary {
+        data: &'a [u8],
+        pos: usize,
+        big_endian: bool,
+    },
+}
+
+impl BodyReader<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, String> {
+        match self {
+            BodyReader::Ascii(tokens) => {
+                let token = tokens.next().ok_or("unexpected end of data")?;
+                token
+                    .parse()
+                    .map_err(|_| format!("invalid number '{token}'"))
+            }
+            BodyReader::Binary {
+                data,
+                pos,
+                big_endian,
+            } => {
+                let size = ty.size();
+                let bytes = data
+                    .get(*pos..*pos + size)
+                    .ok_or("unexpected end of data")?;
+                *pos += size;
+
+                let mut buf = [0u8; 8];
+                buf[..size].copy_from_slice(bytes);
+                if *big_endian {
+                    buf[..size].reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buf[0] as i8 as f64,
+                    ScalarType::U8 => buf[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    ScalarType::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+
+    /// Read all values of a property, which is a single value unless the property is a list.
+    fn read_property(&mut self, kind: &PropertyKind, values: &mut Vec<f64>) -> Result<(), String> {
+        values.clear();
+        match *kind {
+            PropertyKind::Scalar(ty) => values.push(self.read(ty)?),
+            PropertyKind::List { count, item } => {
+                let count = self.read(count)?;
+                if count < 0.0 {
+                    return Err(format!("invalid list length {count}"));
+                }
+                for _ in 0..count as usize {
+                    values.push(self.read(item)?);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse(data: &[u8]) -> Result<TriangleMeshBuilder, String> {
+    let header = parse_header(data)?;
+    let body = &data[header.body_start..];
+    let mut reader = match header.format {
+        Format::Ascii => BodyReader::Ascii(
+            std::str::from_utf8(body)
+                .map_err(|_| "ASCII data is not valid text")?
+                .split_ascii_whitespace(),
+        ),
+        Format::BinaryLittleEndian | Format::BinaryBigEndian => BodyReader::Binary {
+            data: body,
+            pos: 0,
+            big_endian: header.format == Format::BinaryBigEndian,
+        },
+    };
+
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut uvs = Vec::new();
+    let mut colors = Vec::new();
+    let mut indices = Vec::new();
+
+    let mut values = Vec::new();
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => {
+                let prop = |names: &[&str]| element.property_index(names);
+                let position = [prop(&["x"]), prop(&["y"]), prop(&["z"])];
+                let [Some(x), Some(y), Some(z)] = position else {
+                    return Err("vertex element lacks x, y or z".to_string());
+                };
+                let normal = [prop(&["nx"]), prop(&["ny"]), prop(&["nz"])];
+                let color = [
+                    prop(&["red", "r", "diffuse_red"]),
+                    prop(&["green", "g", "diffuse_green"]),
+                    prop(&["blue", "b", "diffuse_blue"]),
+                ];
+                let uv = [
+                    prop(&["u", "s", "texture_u"]),
+                    prop(&["v", "t", "texture_v"]),
+                ];
+
+                let mut vertex = vec![0.0; element.properties.len()];
+                for _ in 0..element.count {
+                    for (i, property) in element.properties.iter().enumerate() {
+                        reader.read_property(&property.kind, &mut values)?;
+                        vertex[i] = values.first().copied().unwrap_or(0.0);
+                    }
+
+                    positions.push(Vector3::new(vertex[x], vertex[y], vertex[z]).cast());
+                    if let [Some(nx), Some(ny), Some(nz)] = normal {
+                        normals.push(Vector3::new(vertex[nx], vertex[ny], vertex[nz]).cast());
+                    }
+                    if let [Some(u), Some(v)] = uv {
+                        uvs.push(Vector2::new(vertex[u], vertex[v]).cast());
+                    }
+                    if let [Some(r), Some(g), Some(b)] = color {
+                        let c = Vector3::new(r, g, b).map(|i| {
+                            let PropertyKind::Scalar(ty) = element.properties[i].kind else {
+                                return 0.0;
+                            };
+                            // Integer colors are stored in sRGB, floating point ones are
+                            // taken to be linear already. Negative values of signed types
+                            // are clamped to black.
+                            match ty.color_scale() {
+                                Some(scale) => (vertex[i] / scale).clamp(0.0, 1.0).powf(2.2) as f32,
+                                None => vertex[i] as f32,
+                            }
+                        });
+                        colors.push(c);
+                    }
+                }
+            }
+            "face" => {
+                let list = element
+                    .property_index(&["vertex_indices", "vertex_index"])
+                    .ok_or("face element lacks vertex_indices")?;
+                for _ in 0..element.count {
+                    for (i, property) in element.properties.iter().enumerate() {
+                        reader.read_property(&property.kind, &mut values)?;
+                        if i != list {
+                            continue;
+                        }
+                        if values.len() < 3 {
+                            return Err(format!(
+                                "face needs at least 3 vertices, got {}",
+                                values.len()
+                            ));
+                        }
+                        if let Some(index) = values.iter().find(|&&index| index < 0.0) {
+                            return Err(format!("invalid vertex index {index}"));
+                        }
+                        // Triangulate the polygon as a fan around its first vertex.
+                        for j in 1..values.len() - 1 {
+                            indices.push([values[0], values[j], values[j + 1]].map(|i| i as u32));
+                        }
+                    }
+                }
+            }
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        reader.read_property(&property.kind, &mut values)?;
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(index) = indices
+        .iter()
+        .flatten()
+        .find(|&&i| i as usize >= positions.len())
+    {
+        return Err(format!(
+            "vertex index {index} out of range, {} vertices defined",
+            positions.len()
+        ));
+    }
+
+    Ok(TriangleMeshBuilder::new(positions, indices)
+        .normals(normals)
+        .uvs(uvs)
+        .colors(colors))
+}
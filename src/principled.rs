@@ -0,0 +1,524 @@
+//! A principled "uber" material in the spirit of the Disney and OpenPBR surface models, covering
+//! most real world surfaces with one set of intuitive parameters.
+//!
+//! The material is a mix of lobes: a clearcoat layer on top of a base which is a blend of metal,
+//! glass-like transmission and a dielectric with a glossy specular reflection over diffuse and
+//! sheen scattering. Each scattered direction is sampled from one lobe chosen at random, and
+//! weighted by the whole material over the combined density of all lobes, which is unbiased
+//! whichever lobe was chosen and keeps variance low where lobes overlap.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::hittable::HitRecord;
+use crate::material::{outgoing_direction, Material, ScatterResult};
+use crate::microfacet::{self, fresnel_dielectric, fresnel_schlick};
+use crate::onb::Onb;
+use crate::random_utils::{random_cosine_direction, random_float};
+use crate::ray::Ray;
+use crate::texture::{IntoTexture, Texture};
+
+/// A principled material, configured with a [`PrincipledBuilder`]. Every parameter may vary over
+/// the surface; scalar parameters are read from the first channel of their texture.
+pub struct Principled {
+    base_color: Arc<dyn Texture>,
+    metallic: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    sheen: Arc<dyn Texture>,
+    clearcoat: Arc<dyn Texture>,
+    clearcoat_roughness: Arc<dyn Texture>,
+    transmission: Arc<dyn Texture>,
+    ior: Arc<dyn Texture>,
+    emission: Arc<dyn Texture>,
+    absorption: Vector3<f32>,
+}
+
+impl Principled {
+    fn params(&self, rec: &HitRecord) -> Params {
+        let value = |tex: &Arc<dyn Texture>| tex.value(rec.u, rec.v, &rec.p);
+        let scalar = |tex: &Arc<dyn Texture>| value(tex).x.clamp(0.0, 1.0);
+
+        let ior = value(&self.ior).x.max(1.0);
+        Params {
+            base_color: value(&self.base_color),
+            metallic: scalar(&self.metallic),
+            alpha: microfacet::roughness_to_alpha(scalar(&self.roughness)),
+            // A specular level of 0.5 corresponds to the reflectance of common dielectrics.
+            specular_f0: 0.08 * scalar(&self.specular),
+            sheen: scalar(&self.sheen),
+            clearcoat: scalar(&self.clearcoat),
+            clearcoat_alpha: microfacet::roughness_to_alpha(scalar(&self.clearcoat_roughness)),
+            transmission: scalar(&self.transmission),
+            eta: if rec.front_face { ior } else { 1.0 / ior },
+        }
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterResult> {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+
+        let bsdf = Bsdf::new(self.params(rec), &wo)?;
+        let (wi, mut attenuation) = bsdf.sample(&wo)?;
+
+        // A ray hitting the back face has travelled through the inside of the material.
+        if !rec.front_face {
+            let distance = rec.t * r_in.direction().magnitude();
+            attenuation.component_mul_assign(&self.absorption.map(|a| (-a * distance).exp()));
+        }
+
+        Some(ScatterResult {
+            attenuation,
+            scattered: Ray::new(rec.p, onb.transform(&wi), r_in.time()),
+            exit: None,
+        })
+    }
+
+    fn emitted(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32> {
+        self.emission.value(u, v, p)
+    }
+
+    fn eval(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> Vector3<f32> {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+
+        let Some(bsdf) = Bsdf::new(self.params(rec), &wo) else {
+            return Vector3::zeros();
+        };
+        let mut value = bsdf.eval(&wo, &onb.to_basis(wi));
+
+        // A ray hitting the back face has travelled through the inside of the material.
+        if !rec.front_face {
+            let distance = rec.t * r_in.direction().magnitude();
+            value.component_mul_assign(&self.absorption.map(|a| (-a * distance).exp()));
+        }
+        value
+    }
+
+    fn pdf(&self, r_in: &Ray, rec: &HitRecord, wi: &Vector3<f32>) -> f32 {
+        let onb = Onb::new(&rec.normal);
+        let wo = outgoing_direction(r_in, &onb);
+
+        Bsdf::new(self.params(rec), &wo).map_or(0.0, |bsdf| bsdf.pdf(&wo, &onb.to_basis(wi)))
+    }
+}
+
+pub struct PrincipledBuilder {
+    base_color: Arc<dyn Texture>,
+    metallic: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    sheen: Arc<dyn Texture>,
+    clearcoat: Arc<dyn Texture>,
+    clearcoat_roughness: Arc<dyn Texture>,
+    transmission: Arc<dyn Texture>,
+    ior: Arc<dyn Texture>,
+    emission: Arc<dyn Texture>,
+    absorption: Vector3<f32>,
+}
+
+impl PrincipledBuilder {
+    /// Create a new builder for a light grey, semi-glossy plastic.
+    pub fn new() -> Self {
+        Self {
+            base_color: Vector3::new(0.8, 0.8, 0.8).into_texture(),
+            metallic: 0.0.into_texture(),
+            roughness: 0.5.into_texture(),
+            specular: 0.5.into_texture(),
+            sheen: 0.0.into_texture(),
+            clearcoat: 0.0.into_texture(),
+            clearcoat_roughness: 0.03.into_texture(),
+            transmission: 0.0.into_texture(),
+            ior: 1.5.into_texture(),
+            emission: Vector3::zeros().into_texture(),
+            absorption: Vector3::zeros(),
+        }
+    }
+
+    /// The diffuse color of dielectrics, the reflectance of metals and the tint of transmission.
+    pub fn base_color(mut self, base_color: impl IntoTexture) -> Self {
+        self.base_color = base_color.into_texture();
+        self
+    }
+
+    /// Blends from a dielectric (0) to a metal (1).
+    pub fn metallic(mut self, metallic: impl IntoTexture) -> Self {
+        self.metallic = metallic.into_texture();
+        self
+    }
+
+    /// Microfacet roughness of the base, from mirror-like (0) to fully rough (1).
+    pub fn roughness(mut self, roughness: impl IntoTexture) -> Self {
+        self.roughness = roughness.into_texture();
+        self
+    }
+
+    /// Strength of the specular reflection of dielectrics, with 0.5 matching an index of
+    /// refraction of 1.5.
+    pub fn specular(mut self, specular: impl IntoTexture) -> Self {
+        self.specular = specular.into_texture();
+        self
+    }
+
+    /// Strength of the soft retro-reflective rim seen on cloth.
+    pub fn sheen(mut self, sheen: impl IntoTexture) -> Self {
+        self.sheen = sheen.into_texture();
+        self
+    }
+
+    /// Strength of a clear varnish layer on top of the base.
+    pub fn clearcoat(mut self, clearcoat: impl IntoTexture) -> Self {
+        self.clearcoat = clearcoat.into_texture();
+        self
+    }
+
+    pub fn clearcoat_roughness(mut self, clearcoat_roughness: impl IntoTexture) -> Self {
+        self.clearcoat_roughness = clearcoat_roughness.into_texture();
+        self
+    }
+
+    /// Blends from an opaque dielectric (0) to a transparent one (1), refracting light through
+    /// the surface.
+    pub fn transmission(mut self, transmission: impl IntoTexture) -> Self {
+        self.transmission = transmission.into_texture();
+        self
+    }
+
+    /// Index of refraction of transmitted light.
+    pub fn ior(mut self, ior: impl IntoTexture) -> Self {
+        self.ior = ior.into_texture();
+        self
+    }
+
+    /// Light emitted by the surface.
+    pub fn emission(mut self, emission: impl IntoTexture) -> Self {
+        self.emission = emission.into_texture();
+        self
+    }
+
+    /// Absorb transmitted light inside the material following the Beer–Lambert law, as in
+    /// [`Dielectric::with_absorption`](crate::material::Dielectric::with_absorption).
+    pub fn absorption(mut self, absorption: Vector3<f32>) -> Self {
+        self.absorption = absorption;
+        self
+    }
+
+    /// Absorb transmitted light inside the material so that white light has taken on the color
+    /// `color` after travelling the distance `distance`.
+    ///
+    /// Panics if `distance` is not positive.
+    pub fn color_at_distance(self, color: Vector3<f32>, distance: f32) -> Self {
+        assert!(distance > 0.0, "color distance must be positive");
+        let absorption = color.map(|c| -c.clamp(1e-6, 1.0).ln() / distance);
+        self.absorption(absorption)
+    }
+
+    pub fn build(self) -> Principled {
+        Principled {
+            base_color: self.base_color,
+            metallic: self.metallic,
+            roughness: self.roughness,
+            specular: self.specular,
+            sheen: self.sheen,
+            clearcoat: self.clearcoat,
+            clearcoat_roughness: self.clearcoat_roughness,
+            transmission: self.transmission,
+            ior: self.ior,
+            emission: self.emission,
+            absorption: self.absorption,
+        }
+    }
+}
+
+impl Default for PrincipledBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The material parameters evaluated at a hit point.
+struct Params {
+    base_color: Vector3<f32>,
+    metallic: f32,
+    alpha: f32,
+    specular_f0: f32,
+    sheen: f32,
+    clearcoat: f32,
+    clearcoat_alpha: f32,
+    transmission: f32,
+    /// Refractive index on the far side of the surface over the one on the near side.
+    eta: f32,
+}
+
+#[derive(Clone, Copy)]
+enum Lobe {
+    Clearcoat,
+    Specular,
+    Transmission,
+    Diffuse,
+    Sheen,
+}
+
+const LOBES: [Lobe; 5] = [
+    Lobe::Clearcoat,
+    Lobe::Specular,
+    Lobe::Transmission,
+    Lobe::Diffuse,
+    Lobe::Sheen,
+];
+
+/// The scattering function of the material at a hit point, in the local shading frame where the
+/// normal is along z and the outgoing direction `wo` lies above the surface.
+struct Bsdf {
+    params: Params,
+    /// The fraction of light passing through the clearcoat on its way into and out of the base.
+    base_weight: f32,
+    /// The probability of sampling each lobe, in the order of [`LOBES`].
+    probabilities: [f32; 5],
+}
+
+impl Bsdf {
+    /// Returns `None` for a material which doesn't scatter any light.
+    fn new(params: Params, wo: &Vector3<f32>) -> Option<Self> {
+        let p = &params;
+        let clearcoat = p.clearcoat * fresnel_schlick(&Vector3::repeat(0.04), wo.z).x;
+        let base_weight = 1.0 - clearcoat;
+        let dielectric = (1.0 - p.metallic) * (1.0 - p.transmission);
+
+        // Rough estimates of the amount of light scattered by each lobe.
+        let specular = p.metallic * fresnel_schlick(&p.base_color, wo.z)
+            + dielectric * fresnel_schlick(&Vector3::repeat(p.specular_f0), wo.z);
+        let diffuse = dielectric
+            * (1.0 - fresnel_schlick(&Vector3::repeat(p.specular_f0), wo.z).x)
+            * p.base_color.max();
+        let mut probabilities = [
+            clearcoat,
+            base_weight * specular.max(),
+            base_weight * (1.0 - p.metallic) * p.transmission,
+            base_weight * diffuse,
+            base_weight * (1.0 - p.metallic) * 0.1 * p.sheen,
+        ];
+
+        let total: f32 = probabilities.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        for probability in probabilities.iter_mut() {
+            *probability /= total;
+        }
+
+        Some(Self {
+            params,
+            base_weight,
+            probabilities,
+        })
+    }
+
+    /// Samples an incoming direction, returning it with the throughput weight of the sample.
+    fn sample(&self, wo: &Vector3<f32>) -> Option<(Vector3<f32>, Vector3<f32>)> {
+        let mut xi = random_float();
+        let lobe = LOBES
+            .iter()
+            .zip(self.probabilities)
+            .find(|&(_, probability)| {
+                xi -= probability;
+                xi < 0.0
+            })
+            .map_or(Lobe::Sheen, |(&lobe, _)| lobe);
+
+        let wi = self.sample_lobe(lobe, wo)?;
+        let pdf = self.pdf(wo, &wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((wi, self.eval(wo, &wi) / pdf))
+    }
+
+    /// The scattering function times the cosine of the incoming direction, summed over all
+    /// lobes.
+    fn eval(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> Vector3<f32> {
+        LOBES
+            .iter()
+            .zip(self.probabilities)
+            .filter(|&(_, probability)| probability > 0.0)
+            .map(|(&lobe, _)| self.eval_lobe(lobe, wo, wi))
+            .sum()
+    }
+
+    /// The density of sampling `wi`, over the random choice of lobe.
+    fn pdf(&self, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        LOBES
+            .iter()
+            .zip(self.probabilities)
+            .filter(|&(_, probability)| probability > 0.0)
+            .map(|(&lobe, probability)| probability * self.pdf_lobe(lobe, wo, wi))
+            .sum()
+    }
+
+    fn sample_lobe(&self, lobe: Lobe, wo: &Vector3<f32>) -> Option<Vector3<f32>> {
+        let p = &self.params;
+        let wi = match lobe {
+            Lobe::Clearcoat => sample_reflection(wo, p.clearcoat_alpha),
+            Lobe::Specular => sample_reflection(wo, p.alpha),
+            Lobe::Transmission => {
+                let m = microfacet::sample_visible_normal(
+                    wo,
+                    p.alpha,
+                    p.alpha,
+                    random_float(),
+                    random_float(),
+                );
+                let cos_theta_o = wo.dot(&m);
+                if random_float() < fresnel_dielectric(cos_theta_o, p.eta) {
+                    2.0 * cos_theta_o * m - wo
+                } else {
+                    // Refract about the microfacet normal; total internal reflection was handled
+                    // by the Fresnel term above.
+                    let sin2_theta_t = (1.0 - cos_theta_o * cos_theta_o) / (p.eta * p.eta);
+                    let cos_theta_t = (1.0 - sin2_theta_t).max(0.0).sqrt();
+                    -wo / p.eta + (cos_theta_o / p.eta - cos_theta_t) * m
+                }
+            }
+            Lobe::Diffuse | Lobe::Sheen => random_cosine_direction(),
+        };
+        Some(wi).filter(|wi| wi.z != 0.0)
+    }
+
+    fn eval_lobe(&self, lobe: Lobe, wo: &Vector3<f32>, wi: &Vector3<f32>) -> Vector3<f32> {
+        let p = &self.params;
+        let reflected = wi.z > 0.0;
+        match lobe {
+            Lobe::Clearcoat if reflected => {
+                let m = (wo + wi).normalize();
+                let fresnel = fresnel_schlick(&Vector3::repeat(0.04), wo.dot(&m));
+                p.clearcoat * fresnel * microfacet_reflection(wo, wi, &m, p.clearcoat_alpha)
+            }
+            Lobe::Specular if reflected => {
+                let m = (wo + wi).normalize();
+                let cos_theta_o = wo.dot(&m);
+                let dielectric = (1.0 - p.metallic) * (1.0 - p.transmission);
+                let fresnel = p.metallic * fresnel_schlick(&p.base_color, cos_theta_o)
+                    + dielectric * fresnel_schlick(&Vector3::repeat(p.specular_f0), cos_theta_o);
+                self.base_weight * fresnel * microfacet_reflection(wo, wi, &m, p.alpha)
+            }
+            Lobe::Transmission => {
+                let weight = self.base_weight * (1.0 - p.metallic) * p.transmission;
+                if reflected {
+                    let m = (wo + wi).normalize();
+                    let fresnel = fresnel_dielectric(wo.dot(&m), p.eta);
+                    Vector3::repeat(weight * fresnel * microfacet_reflection(wo, wi, &m, p.alpha))
+                } else {
+                    let Some(refraction) = Refraction::new(wo, wi, p.eta) else {
+                        return Vector3::zeros();
+                    };
+                    let fresnel = fresnel_dielectric(refraction.cos_theta_o, p.eta);
+                    // Eval and pdf differ only by the shadowing term, as for reflection.
+                    let value = (1.0 - fresnel)
+                        * refraction.visible_normal_pdf(wo, p.alpha)
+                        * refraction.jacobian
+                        * microfacet::g2(wo, wi, p.alpha, p.alpha)
+                        / microfacet::g1(wo, p.alpha, p.alpha);
+                    weight * value * p.base_color
+                }
+            }
+            Lobe::Diffuse if reflected => {
+                let dielectric = (1.0 - p.metallic) * (1.0 - p.transmission);
+                let fresnel = fresnel_schlick(&Vector3::repeat(p.specular_f0), wo.z).x;
+                self.base_weight * dielectric * (1.0 - fresnel) * p.base_color * wi.z / PI
+            }
+            Lobe::Sheen if reflected => {
+                let m = (wo + wi).normalize();
+                let weight = (1.0 - wi.dot(&m)).clamp(0.0, 1.0).powi(5);
+                let sheen = self.base_weight * (1.0 - p.metallic) * p.sheen * weight;
+                Vector3::repeat(sheen * wi.z / PI)
+            }
+            _ => Vector3::zeros(),
+        }
+    }
+
+    fn pdf_lobe(&self, lobe: Lobe, wo: &Vector3<f32>, wi: &Vector3<f32>) -> f32 {
+        let p = &self.params;
+        let reflected = wi.z > 0.0;
+        match lobe {
+            Lobe::Clearcoat if reflected => {
+                microfacet::reflection_pdf(wo, wi, p.clearcoat_alpha, p.clearcoat_alpha)
+            }
+            Lobe::Specular if reflected => microfacet::reflection_pdf(wo, wi, p.alpha, p.alpha),
+            Lobe::Transmission => {
+                if reflected {
+                    let m = (wo + wi).normalize();
+                    fresnel_dielectric(wo.dot(&m), p.eta)
+                        * microfacet::reflection_pdf(wo, wi, p.alpha, p.alpha)
+                } else {
+                    let Some(refraction) = Refraction::new(wo, wi, p.eta) else {
+                        return 0.0;
+                    };
+                    let fresnel = fresnel_dielectric(refraction.cos_theta_o, p.eta);
+                    (1.0 - fresnel)
+                        * refraction.visible_normal_pdf(wo, p.alpha)
+                        * refraction.jacobian
+                }
+            }
+            Lobe::Diffuse | Lobe::Sheen if reflected => wi.z / PI,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Samples a direction reflected about a visible GGX microfacet normal.
+fn sample_reflection(wo: &Vector3<f32>, alpha: f32) -> Vector3<f32> {
+    let m = microfacet::sample_visible_normal(wo, alpha, alpha, random_float(), random_float());
+    2.0 * wo.dot(&m) * m - wo
+}
+
+/// The GGX reflection times the cosine of the incoming direction, without the Fresnel term.
+fn microfacet_reflection(
+    wo: &Vector3<f32>,
+    wi: &Vector3<f32>,
+    m: &Vector3<f32>,
+    alpha: f32,
+) -> f32 {
+    microfacet::d(m, alpha, alpha) * microfacet::g2(wo, wi, alpha, alpha) / (4.0 * wo.z)
+}
+
+/// The microfacet configuration refracting `wo` into `wi`.
+///
+/// Refracted light is not scaled by the squared ratio of refractive indices, so like
+/// [`Dielectric`](crate::material::Dielectric) the material carries importance rather than
+/// radiance through the surface, and the two agree with each other.
+struct Refraction {
+    m: Vector3<f32>,
+    cos_theta_o: f32,
+    /// The change of density from microfacet normals to refracted directions.
+    jacobian: f32,
+}
+
+impl Refraction {
+    fn new(wo: &Vector3<f32>, wi: &Vector3<f32>, eta: f32) -> Option<Self> {
+        let mut m = (wo + eta * wi).normalize();
+        if m.z < 0.0 {
+            m = -m;
+        }
+        let cos_theta_o = wo.dot(&m);
+        let cos_theta_i = wi.dot(&m);
+        if cos_theta_o <= 0.0 || cos_theta_i >= 0.0 {
+            return None;
+        }
+        let denominator = cos_theta_o + eta * cos_theta_i;
+        Some(Self {
+            m,
+            cos_theta_o,
+            jacobian: eta * eta * cos_theta_i.abs() / (denominator * denominator),
+        })
+    }
+
+    /// The density of sampling the microfacet normal from the visible normals seen from `wo`.
+    fn visible_normal_pdf(&self, wo: &Vector3<f32>, alpha: f32) -> f32 {
+        microfacet::g1(wo, alpha, alpha) * self.cos_theta_o * microfacet::d(&self.m, alpha, alpha)
+            / wo.z
+    }
+}
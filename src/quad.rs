@@ -0,0 +1,149 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+
+/// A parallelogram spanned by the edges `u` and `v` from the corner `q`.
+pub struct Quad {
+    q: Vector3<f32>,
+    u: Vector3<f32>,
+    v: Vector3<f32>,
+    w: Vector3<f32>,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    normal: Vector3<f32>,
+    d: f32,
+}
+
+impl Quad {
+    pub fn new(q: Vector3<f32>, u: Vector3<f32>, v: Vector3<f32>, mat: Arc<dyn Material>) -> Self {
+        let n = u.cross(&v);
+        let normal = n.normalize();
+        let d = normal.dot(&q);
+        let w = n / n.dot(&n);
+
+        // Compute the bounding box of all four vertices.
+        let bbox_diagonal1 = Aabb::from_points(q, q + u + v);
+        let bbox_diagonal2 = Aabb::from_points(q + u, q + v);
+        let bbox = Aabb::enclosing(&bbox_diagonal1, &bbox_diagonal2);
+
+        Self {
+            q,
+            u,
+            v,
+            w,
+            mat,
+            bbox,
+            normal,
+            d,
+        }
+    }
+}
+
+impl Hittable for Quad {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let t = hit_plane(r, ray_t, &self.normal, self.d)?;
+
+        // Determine if the hit point lies within the planar shape using its plane coordinates.
+        let intersection = r.at(t);
+        let planar_hitpt_vector = intersection - self.q;
+        let alpha = self.w.dot(&planar_hitpt_vector.cross(&self.v));
+        let beta = self.w.dot(&self.u.cross(&planar_hitpt_vector));
+
+        let unit_interval = Interval::new(0.0, 1.0);
+        if !unit_interval.contains(alpha) || !unit_interval.contains(beta) {
+            return None;
+        }
+
+        let mut rec = HitRecord::new(intersection, t, Arc::clone(&self.mat));
+        rec.u = alpha;
+        rec.v = beta;
+        rec.tangent = self.u;
+        rec.set_face_normal(r, self.normal);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// A flat circular disk facing along `normal`.
+pub struct Disk {
+    center: Vector3<f32>,
+    radius: f32,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+    d: f32,
+}
+
+impl Disk {
+    pub fn new(
+        center: Vector3<f32>,
+        normal: Vector3<f32>,
+        radius: f32,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let onb = Onb::new(&normal);
+        // The extent of the disk along each axis depends on how far it is tilted away from it.
+        let extent = onb.w().map(|n| radius * (1.0 - n * n).max(0.0).sqrt());
+        Self {
+            center,
+            radius,
+            onb,
+            mat,
+            bbox: Aabb::from_points(center - extent, center + extent),
+            d: onb.w().dot(&center),
+        }
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let t = hit_plane(r, ray_t, &self.onb.w(), self.d)?;
+
+        let intersection = r.at(t);
+        let local = self.onb.to_basis(&(intersection - self.center));
+        let dist_squared = local.x * local.x + local.y * local.y;
+        if dist_squared > self.radius * self.radius {
+            return None;
+        }
+
+        // Polar coordinates: u runs around the disk, v from the center to the rim.
+        let mut rec = HitRecord::new(intersection, t, Arc::clone(&self.mat));
+        rec.u = (local.y.atan2(local.x) + PI) / (2.0 * PI);
+        rec.v = dist_squared.sqrt() / self.radius;
+        rec.tangent = self.onb.transform(&Vector3::new(-local.y, local.x, 0.0));
+        rec.set_face_normal(r, self.onb.w());
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Intersect a ray with the plane `normal · p = d`, returning the ray parameter of the hit.
+pub(crate) fn hit_plane(r: &Ray, ray_t: Interval, normal: &Vector3<f32>, d: f32) -> Option<f32> {
+    let denom = normal.dot(&r.direction());
+
+    // No hit if the ray is parallel to the plane.
+    if denom.abs() < 1e-8 {
+        return None;
+    }
+
+    // No hit if the hit point parameter t is outside the ray interval.
+    let t = (d - normal.dot(&r.origin())) / denom;
+    if !ray_t.contains(t) {
+        return None;
+    }
+    Some(t)
+}
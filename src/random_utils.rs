@@ -25,3 +25,28 @@ pub fn random_vector_range(min: f32, max: f32) -> Vector3<f32> {
         random_float_range(min, max),
     )
 }
+
+/// Generate a uniformly distributed random direction.
+pub fn random_unit_vector() -> Vector3<f32> {
+    loop {
+        let p: Vector3<f32> = random_vector_range(-1.0, 1.0);
+        let lensq = p.magnitude_squared();
+        if 1e-160 < lensq && lensq <= 1.0 {
+            return p / lensq.sqrt();
+        }
+    }
+}
+
+/// Generate a random direction on the hemisphere around +z, with a density proportional to the
+/// cosine of its angle to z.
+pub fn random_cosine_direction() -> Vector3<f32> {
+    let r1 = random_float();
+    let r2 = random_float();
+
+    let phi = 2.0 * std::f32::consts::PI * r1;
+    let x = phi.cos() * r2.sqrt();
+    let y = phi.sin() * r2.sqrt();
+    let z = (1.0 - r2).sqrt();
+
+    Vector3::new(x, y, z)
+}
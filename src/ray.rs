@@ -1,24 +1,45 @@
 use nalgebra::Vector3;
 
+use crate::spectrum::Wavelengths;
+
 pub struct Ray {
     orig: Vector3<f32>,
     dir: Vector3<f32>,
+    tm: f32,
+    wavelengths: Option<Wavelengths>,
 }
 
 impl Ray {
-    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>) -> Self {
+    pub fn new(origin: Vector3<f32>, direction: Vector3<f32>, time: f32) -> Self {
         Self {
             orig: origin,
             dir: direction,
+            tm: time,
+            wavelengths: None,
         }
     }
 
+    /// Makes the ray carry the wavelengths of a spectral render. Scattered rays inherit the
+    /// wavelengths of the incoming ray unless a material sets them.
+    pub fn with_wavelengths(mut self, wavelengths: Wavelengths) -> Self {
+        self.wavelengths = Some(wavelengths);
+        self
+    }
+
     pub fn origin(&self) -> Vector3<f32> {
         self.orig
     }
     pub fn direction(&self) -> Vector3<f32> {
         self.dir
     }
+    /// The moment within the camera shutter interval at which the ray was cast.
+    pub fn time(&self) -> f32 {
+        self.tm
+    }
+    /// The wavelengths traced in spectral mode, or `None` when rendering in RGB.
+    pub fn wavelengths(&self) -> Option<Wavelengths> {
+        self.wavelengths
+    }
     pub fn at(&self, t: f32) -> Vector3<f32> {
         self.orig + t * self.dir
     }
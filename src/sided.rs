@@ -0,0 +1,79 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+
+/// Controls how the two sides of an object's surface are rendered. The front side is the one
+/// the outward normal points to, e.g. the outside of a closed mesh or the side of a quad from
+/// which its corners run counterclockwise.
+///
+/// Back faces can be culled, making the object invisible from behind, like a one-sided wall
+/// letting the camera see into a room. Each side can also be given its own material, like a
+/// sheet of paper with a different color on each side.
+pub struct Sided {
+    object: Box<dyn Hittable>,
+    cull_back_faces: bool,
+    front: Option<Arc<dyn Material>>,
+    back: Option<Arc<dyn Material>>,
+}
+
+impl Sided {
+    /// Wraps an object, keeping both sides visible with the object's own materials.
+    pub fn new(object: Box<dyn Hittable>) -> Self {
+        Self {
+            object,
+            cull_back_faces: false,
+            front: None,
+            back: None,
+        }
+    }
+
+    /// Makes the back faces invisible, so that rays hitting them go on through.
+    pub fn cull_back_faces(mut self) -> Self {
+        self.cull_back_faces = true;
+        self
+    }
+
+    /// Replaces the material of the front faces.
+    pub fn with_front_material(mut self, mat: Arc<dyn Material>) -> Self {
+        self.front = Some(mat);
+        self
+    }
+
+    /// Replaces the material of the back faces.
+    pub fn with_back_material(mut self, mat: Arc<dyn Material>) -> Self {
+        self.back = Some(mat);
+        self
+    }
+}
+
+impl Hittable for Sided {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let mut ray_t = ray_t;
+        let mut rec = loop {
+            let rec = self.object.hit(r, ray_t)?;
+            if rec.front_face || !self.cull_back_faces {
+                break rec;
+            }
+            // Look for another face behind the culled one.
+            ray_t = Interval::new(rec.t + 0.001, ray_t.max);
+        };
+
+        let mat = if rec.front_face {
+            &self.front
+        } else {
+            &self.back
+        };
+        if let Some(mat) = mat {
+            rec.mat = Arc::clone(mat);
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.object.bounding_box()
+    }
+}
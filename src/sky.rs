@@ -0,0 +1,201 @@
+//! A physically based daylight sky, after Preetham, Shirley and Smits, "A Practical Analytic Model
+//! for Daylight" (1999).
+//!
+//! Radiance is given in units where the sun outside the atmosphere has an irradiance of π, so
+//! that a white diffuse surface facing a high sun has a brightness close to one. The y axis
+//! points up.
+
+use std::f32::consts::{FRAC_PI_2, PI};
+
+use nalgebra::{Matrix3x4, Vector3, Vector4};
+
+use crate::environment::{self, Environment, LatLongDistribution};
+use crate::light::DirectionalLight;
+use crate::spectrum::XYZ_TO_RGB;
+
+/// Illuminance of the sun outside the atmosphere, in kilolux, which the model's luminances in
+/// kcd/m² are relative to.
+const SOLAR_ILLUMINANCE: f32 = 128.0;
+
+/// Columns and rows of the table of the sky's luminance used to sample it.
+const SAMPLING_TABLE_SIZE: (usize, usize) = (128, 64);
+
+/// Apparent diameter of the sun in degrees.
+const SUN_ANGULAR_DIAMETER: f32 = 0.53;
+
+/// Wavelengths in micrometers at which the transmittance of the atmosphere to the sun is
+/// evaluated for the red, green and blue channels.
+const SUN_WAVELENGTHS: [f32; 3] = [0.65, 0.55, 0.45];
+
+/// Coefficients giving the chromaticity x of the zenith from the turbidity and the sun zenith
+/// angle.
+#[rustfmt::skip]
+const ZENITH_X: Matrix3x4<f32> = Matrix3x4::new(
+    0.00166, -0.00375, 0.00209, 0.0,
+    -0.02903, 0.06377, -0.03202, 0.00394,
+    0.11693, -0.21196, 0.06052, 0.25886,
+);
+
+/// Coefficients giving the chromaticity y of the zenith from the turbidity and the sun zenith
+/// angle.
+#[rustfmt::skip]
+const ZENITH_Y: Matrix3x4<f32> = Matrix3x4::new(
+    0.00275, -0.00610, 0.00317, 0.0,
+    -0.04214, 0.08970, -0.04153, 0.00516,
+    0.15346, -0.26756, 0.06670, 0.26688,
+);
+
+/// A clear sky lit by the sun, brightest around the sun and towards the horizon, which reddens
+/// as the sun sets.
+///
+/// The sky gives the scene's background without the sun itself, and is sampled following its
+/// brightness to light the scene directly. Light the scene with the matching [`Sky::sun`] as
+/// well, which is sampled directly for sharp shadows.
+pub struct Sky {
+    /// Unit direction towards the sun.
+    sun_direction: Vector3<f32>,
+    turbidity: f32,
+    /// Perez distribution coefficients A to E for the luminance Y and the chromaticities x and y.
+    perez: [[f32; 5]; 3],
+    /// Luminance Y and chromaticities x and y at the zenith.
+    zenith: Vector3<f32>,
+    intensity: f32,
+    distribution: Option<LatLongDistribution>,
+}
+
+impl Sky {
+    /// Creates the sky with the sun at `elevation` degrees above the horizon and `azimuth`
+    /// degrees clockwise from the -z axis seen from above, i.e. turning towards +x. The sun is
+    /// kept between the horizon and the zenith.
+    ///
+    /// `turbidity` measures the haze, from 2 for a very clear sky to about 10 for a hazy one.
+    pub fn new(elevation: f32, azimuth: f32, turbidity: f32) -> Self {
+        let elevation = elevation.clamp(0.0, 90.0).to_radians();
+        let azimuth = azimuth.to_radians();
+        let sun_direction = Vector3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            -elevation.cos() * azimuth.cos(),
+        );
+        let t = turbidity.clamp(1.7, 10.0);
+        let theta_s = FRAC_PI_2 - elevation;
+
+        let perez = [
+            [
+                0.1787 * t - 1.4630,
+                -0.3554 * t + 0.4275,
+                -0.0227 * t + 5.3251,
+                0.1206 * t - 2.5771,
+                -0.0670 * t + 0.3703,
+            ],
+            [
+                -0.0193 * t - 0.2592,
+                -0.0665 * t + 0.0008,
+                -0.0004 * t + 0.2125,
+                -0.0641 * t - 0.8989,
+                -0.0033 * t + 0.0452,
+            ],
+            [
+                -0.0167 * t - 0.2608,
+                -0.0950 * t + 0.0092,
+                -0.0079 * t + 0.2102,
+                -0.0441 * t - 1.6537,
+                -0.0109 * t + 0.0529,
+            ],
+        ];
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+        let turbidities = Vector3::new(t * t, t, 1.0).transpose();
+        let angles = Vector4::new(theta_s.powi(3), theta_s.powi(2), theta_s, 1.0);
+        let zenith = Vector3::new(
+            luminance,
+            (turbidities * ZENITH_X * angles).x,
+            (turbidities * ZENITH_Y * angles).x,
+        );
+
+        let mut sky = Self {
+            sun_direction,
+            turbidity: t,
+            perez,
+            zenith,
+            intensity: 1.0,
+            distribution: None,
+        };
+        let (width, height) = SAMPLING_TABLE_SIZE;
+        sky.distribution = LatLongDistribution::new(width, height, |i, j| {
+            let theta = (j as f32 + 0.5) / height as f32 * PI;
+            let phi = ((i as f32 + 0.5) / width as f32 - 0.5) * 2.0 * PI;
+            let direction = Vector3::new(
+                theta.sin() * phi.sin(),
+                theta.cos(),
+                -theta.sin() * phi.cos(),
+            );
+            environment::luminance(&sky.radiance(&direction))
+        });
+        sky
+    }
+
+    /// Scales the brightness of the sky and of its sun.
+    pub fn with_intensity(mut self, intensity: f32) -> Self {
+        self.intensity = intensity;
+        self
+    }
+
+    /// The sun of this sky as a light source, with its color after crossing the atmosphere.
+    pub fn sun(&self) -> DirectionalLight {
+        // Relative optical mass of the atmosphere crossed by sunlight, after Kasten (1966).
+        let theta_s = self.sun_direction.y.clamp(0.0, 1.0).acos();
+        let mass = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+
+        // Attenuation by Rayleigh scattering on molecules and by Mie scattering on aerosols.
+        let beta = 0.04608 * self.turbidity - 0.04586;
+        let transmittance = Vector3::from_fn(|i, _| {
+            let lambda = SUN_WAVELENGTHS[i];
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * mass).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * mass).exp();
+            rayleigh * aerosol
+        });
+
+        DirectionalLight::new(
+            -self.sun_direction,
+            self.intensity * PI * transmittance,
+            SUN_ANGULAR_DIAMETER,
+        )
+    }
+
+    /// The Perez distribution of one of the quantities in the direction with the cosine
+    /// `cos_theta` to the zenith, at the angle `gamma` from the sun.
+    fn perez(coefficients: &[f32; 5], cos_theta: f32, gamma: f32) -> f32 {
+        let [a, b, c, d, e] = *coefficients;
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos().powi(2))
+    }
+}
+
+impl Environment for Sky {
+    fn radiance(&self, direction: &Vector3<f32>) -> Vector3<f32> {
+        // The model covers the upper hemisphere; below, the horizon continues.
+        let cos_theta = direction.y.max(1e-3);
+        let gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0).acos();
+        let theta_s = self.sun_direction.y.clamp(0.0, 1.0).acos();
+
+        // Relative to the zenith, which is at the angle theta_s from the sun.
+        let [luminance, x, y] = [0, 1, 2].map(|i| {
+            self.zenith[i] * Sky::perez(&self.perez[i], cos_theta, gamma)
+                / Sky::perez(&self.perez[i], 1.0, theta_s)
+        });
+        let xyz = Vector3::new(x / y * luminance, luminance, (1.0 - x - y) / y * luminance);
+        let rgb = XYZ_TO_RGB * xyz;
+        self.intensity * PI / SOLAR_ILLUMINANCE * rgb.map(|c| c.max(0.0))
+    }
+
+    fn sample(&self) -> Option<(Vector3<f32>, f32)> {
+        self.distribution.as_ref()?.sample()
+    }
+
+    fn pdf(&self, direction: &Vector3<f32>) -> f32 {
+        self.distribution
+            .as_ref()
+            .map_or(0.0, |distribution| distribution.pdf(direction))
+    }
+}
@@ -0,0 +1,170 @@
+//! Spectral rendering: sampling of wavelengths, upsampling of RGB colors to spectra, and
+//! conversion of spectral radiance to RGB through the CIE XYZ color matching functions.
+//!
+//! Each camera path carries a few wavelengths, following hero wavelength sampling (Wilkie et
+//! al., "Hero Wavelength Spectral Sampling", 2014): the hero wavelength is sampled uniformly over
+//! the visible range, and the others are spaced evenly from it, wrapping around the range.
+//! Wavelengths are given in nanometers.
+
+use nalgebra::{Matrix3, Vector3, Vector4};
+
+/// Shortest wavelength sampled.
+pub const LAMBDA_MIN: f32 = 360.0;
+/// Longest wavelength sampled.
+pub const LAMBDA_MAX: f32 = 830.0;
+
+/// The wavelengths traced along a camera path.
+#[derive(Clone, Copy, Debug)]
+pub struct Wavelengths {
+    lambda: Vector4<f32>,
+    hero_only: bool,
+}
+
+impl Wavelengths {
+    /// Samples the hero wavelength from the uniform random number `u`.
+    pub fn sample(u: f32) -> Self {
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let hero = LAMBDA_MIN + u * range;
+        let lambda = Vector4::from_fn(|i, _| {
+            LAMBDA_MIN + (hero - LAMBDA_MIN + i as f32 * range / 4.0).rem_euclid(range)
+        });
+        Self {
+            lambda,
+            hero_only: false,
+        }
+    }
+
+    /// The sampled wavelengths, starting with the hero wavelength.
+    pub fn lambda(&self) -> Vector4<f32> {
+        self.lambda
+    }
+
+    pub fn hero(&self) -> f32 {
+        self.lambda[0]
+    }
+
+    /// Whether the other wavelengths have been dropped by [`Wavelengths::terminate_secondary`].
+    pub fn is_hero_only(&self) -> bool {
+        self.hero_only
+    }
+
+    /// Keeps tracing only the hero wavelength, for scattering whose direction depends on the
+    /// wavelength, such as dispersive refraction. The camera then counts the hero wavelength for
+    /// all of them.
+    pub fn terminate_secondary(self) -> Self {
+        Self {
+            hero_only: true,
+            ..self
+        }
+    }
+}
+
+/// The CIE 1931 standard observer color matching functions at `lambda`, using the multi-lobe
+/// Gaussian fit of Wyman et al., "Simple Analytic Approximations to the CIE XYZ Color Matching
+/// Functions" (2013).
+pub fn cie_xyz(lambda: f32) -> Vector3<f32> {
+    let g = |mu: f32, sigma_low: f32, sigma_high: f32| {
+        let sigma = if lambda < mu { sigma_low } else { sigma_high };
+        (-0.5 * ((lambda - mu) / sigma).powi(2)).exp()
+    };
+    Vector3::new(
+        1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2),
+        0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1),
+        1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8),
+    )
+}
+
+/// Converts from CIE XYZ to linear sRGB (Rec. 709 primaries).
+#[rustfmt::skip]
+pub(crate) const XYZ_TO_RGB: Matrix3<f32> = Matrix3::new(
+    3.240_454, -1.537_138, -0.498_531,
+    -0.969_266, 1.876_011, 0.041_556,
+    0.055_643, -0.204_026, 1.057_225,
+);
+
+/// Upsamples RGB colors to smooth spectra and converts sampled spectral radiance back to RGB,
+/// such that the two conversions round trip.
+///
+/// Spectra are blends of three smooth basis functions covering the long, middle and short
+/// wavelengths, which sum to one everywhere, so that white is a constant spectrum. The RGB
+/// output is balanced so that a constant spectrum of one shows as white.
+pub struct SpectralConverter {
+    /// Maps RGB colors to the weights of the basis functions.
+    rgb_to_basis: Matrix3<f32>,
+    /// Maps CIE XYZ to the white balanced RGB output.
+    xyz_to_rgb: Matrix3<f32>,
+}
+
+impl SpectralConverter {
+    pub fn new() -> Self {
+        // Integrate the color matching functions against each basis function.
+        let mut basis_xyz = Matrix3::zeros();
+        let mut y_integral = 0.0;
+        let mut lambda = LAMBDA_MIN;
+        while lambda <= LAMBDA_MAX {
+            let xyz = cie_xyz(lambda);
+            basis_xyz += xyz * basis(lambda).transpose();
+            y_integral += xyz.y;
+            lambda += 1.0;
+        }
+        let basis_rgb = XYZ_TO_RGB * basis_xyz / y_integral;
+
+        // A constant spectrum, the sum of the basis functions, should show as white.
+        let white = basis_rgb.column_sum();
+        let white_balance = Matrix3::from_diagonal(&white.map(|c| 1.0 / c));
+        let basis_rgb = white_balance * basis_rgb;
+
+        Self {
+            rgb_to_basis: basis_rgb
+                .try_inverse()
+                .expect("spectral basis functions are independent"),
+            xyz_to_rgb: white_balance * XYZ_TO_RGB / y_integral,
+        }
+    }
+
+    /// Evaluates the spectrum of the RGB color `rgb` at the sampled wavelengths. The spectrum is
+    /// clamped to be non-negative, which shifts the most saturated colors slightly.
+    pub fn upsample(&self, rgb: &Vector3<f32>, wavelengths: &Wavelengths) -> Vector4<f32> {
+        let weights = self.rgb_to_basis * rgb;
+        wavelengths
+            .lambda()
+            .map(|lambda| weights.dot(&basis(lambda)).max(0.0))
+    }
+
+    /// Estimates the RGB color of the radiance `radiance` sampled at `wavelengths`.
+    pub fn to_rgb(&self, radiance: &Vector4<f32>, wavelengths: &Wavelengths) -> Vector3<f32> {
+        // Each wavelength has the uniform density 1 / range, and the estimates of the wavelengths
+        // traced are averaged.
+        let range = LAMBDA_MAX - LAMBDA_MIN;
+        let xyz = wavelengths
+            .lambda()
+            .iter()
+            .zip(radiance.iter())
+            .map(|(&lambda, &l)| l * cie_xyz(lambda))
+            .sum::<Vector3<f32>>()
+            * range
+            / 4.0;
+        self.xyz_to_rgb * xyz
+    }
+}
+
+impl Default for SpectralConverter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The basis functions of the long, middle and short wavelengths at `lambda`, blending smoothly
+/// around 490 and 585 nm.
+fn basis(lambda: f32) -> Vector3<f32> {
+    let step = |center: f32| {
+        let t = ((lambda - center) / 30.0 + 0.5).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    };
+    let (short_to_middle, middle_to_long) = (step(490.0), step(585.0));
+    Vector3::new(
+        middle_to_long,
+        short_to_middle - middle_to_long,
+        1.0 - short_to_middle,
+    )
+}
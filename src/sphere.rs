@@ -2,31 +2,70 @@ use std::sync::Arc;
 
 use nalgebra::Vector3;
 
+use crate::aabb::Aabb;
 use crate::hittable::{HitRecord, Hittable};
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::Ray;
 
 pub struct Sphere {
-    center: Vector3<f32>,
+    /// The center at time 0, moving along the ray direction to reach its position at time 1.
+    center: Ray,
     radius: f32,
     mat: Arc<dyn Material>,
+    bbox: Aabb,
 }
 
 impl Sphere {
     /// Creates a new sphere from with a given position, size and material.
     pub fn new(center: Vector3<f32>, radius: f32, mat: Arc<dyn Material>) -> Self {
+        let rvec = Vector3::new(radius, radius, radius);
         Self {
-            center,
+            center: Ray::new(center, Vector3::zeros(), 0.0),
             radius,
             mat,
+            bbox: Aabb::from_points(center - rvec, center + rvec),
         }
     }
+
+    /// Creates a sphere moving linearly from `center1` at time 0 to `center2` at time 1, with a
+    /// bounding box enclosing the whole motion.
+    pub fn moving(
+        center1: Vector3<f32>,
+        center2: Vector3<f32>,
+        radius: f32,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let rvec = Vector3::new(radius, radius, radius);
+        let box1 = Aabb::from_points(center1 - rvec, center1 + rvec);
+        let box2 = Aabb::from_points(center2 - rvec, center2 + rvec);
+        Self {
+            center: Ray::new(center1, center2 - center1, 0.0),
+            radius,
+            mat,
+            bbox: Aabb::enclosing(&box1, &box2),
+        }
+    }
+
+    /// Returns the (u, v) surface coordinates for a point `p` on the unit sphere centered at the
+    /// origin.
+    ///
+    /// u: returned value [0,1] of angle around the Y axis from X=-1.
+    /// v: returned value [0,1] of angle from Y=-1 to Y=+1.
+    fn get_sphere_uv(p: &Vector3<f32>) -> (f32, f32) {
+        let theta = (-p.y).acos();
+        let phi = (-p.z).atan2(p.x) + std::f32::consts::PI;
+        (
+            phi / (2.0 * std::f32::consts::PI),
+            theta / std::f32::consts::PI,
+        )
+    }
 }
 
 impl Hittable for Sphere {
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
-        let oc = self.center - r.origin();
+        let current_center = self.center.at(r.time());
+        let oc = current_center - r.origin();
         let a = r.direction().magnitude_squared();
         let h = r.direction().dot(&oc);
         let c = oc.magnitude_squared() - self.radius * self.radius;
@@ -47,8 +86,14 @@ impl Hittable for Sphere {
         }
 
         let mut rec = HitRecord::new(r.at(root), root, Arc::clone(&self.mat));
-        let outward_normal = (rec.p - self.center) / self.radius;
+        let outward_normal = (rec.p - current_center) / self.radius;
         rec.set_face_normal(r, outward_normal);
+        (rec.u, rec.v) = Sphere::get_sphere_uv(&outward_normal);
+        rec.tangent = Vector3::new(outward_normal.z, 0.0, -outward_normal.x);
         Some(rec)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
 }
@@ -0,0 +1,157 @@
+//! Loader for STL meshes, in ASCII or binary encoding.
+//!
+//! STL stores every triangle with its own three vertices and a facet normal, so the resulting
+//! mesh is flat shaded and the normals in the file are ignored in favor of the vertex winding.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::material::Material;
+use crate::mesh::{TriangleMesh, TriangleMeshBuilder};
+
+#[derive(Debug)]
+pub enum StlError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The file is neither valid ASCII nor binary STL.
+    Parse { path: PathBuf, message: String },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StlError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            StlError::Parse { path, message } => write!(f, "{}: {message}", path.display()),
+        }
+    }
+}
+
+impl std::error::Error for StlError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StlError::Io { source, .. } => Some(source),
+            StlError::Parse { .. } => None,
+        }
+    }
+}
+
+/// Load an STL file as a triangle mesh with the material `mat`.
+pub fn load(path: impl AsRef<Path>, mat: Arc<dyn Material>) -> Result<TriangleMesh, StlError> {
+    let path = path.as_ref();
+    let data = fs::read(path).map_err(|source| StlError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+
+    let positions = if is_binary(&data) {
+        Ok(parse_binary(&data))
+    } else {
+        parse_ascii(&data)
+    }
+    .map_err(|message| StlError::Parse {
+        path: path.to_path_buf(),
+        message,
+    })?;
+
+    let indices = (0..positions.len() as u32 / 3)
+        .map(|i| [3 * i, 3 * i + 1, 3 * i + 2])
+        .collect();
+    Ok(TriangleMeshBuilder::new(positions, indices).build(mat))
+}
+
+const BINARY_HEADER_SIZE: usize = 84;
+const BINARY_TRIANGLE_SIZE: usize = 50;
+
+/// Binary files may start with "solid" just like ASCII files, so they're told apart by whether
+/// the first line is followed by ASCII facets, and whether the file is large enough for the
+/// triangle count in the binary header. Some exporters append data after the triangles.
+fn is_binary(data: &[u8]) -> bool {
+    if data.len() < BINARY_HEADER_SIZE || is_ascii(data) {
+        return false;
+    }
+    data.len() >= BINARY_HEADER_SIZE + binary_triangle_count(data) * BINARY_TRIANGLE_SIZE
+}
+
+/// Whether the file starts with "solid" and its second line with a facet, or with the end of an
+/// empty solid.
+fn is_ascii(data: &[u8]) -> bool {
+    let data = data.trim_ascii_start();
+    if !data.starts_with(b"solid") {
+        return false;
+    }
+    let Some(end_of_line) = data.iter().position(|&b| b == b'\n') else {
+        return false;
+    };
+    let next_line = data[end_of_line..].trim_ascii_start();
+    next_line.starts_with(b"facet") || next_line.starts_with(b"endsolid")
+}
+
+fn binary_triangle_count(data: &[u8]) -> usize {
+    u32::from_le_bytes([data[80], data[81], data[82], data[83]]) as usize
+}
+
+fn parse_binary(data: &[u8]) -> Vec<Vector3<f32>> {
+    let mut positions = Vec::new();
+    let triangles = data[BINARY_HEADER_SIZE..]
+        .chunks_exact(BINARY_TRIANGLE_SIZE)
+        .take(binary_triangle_count(data));
+    for triangle in triangles {
+        let read_f32 = |offset: usize| {
+            let bytes = [
+                triangle[offset],
+                triangle[offset + 1],
+                triangle[offset + 2],
+                triangle[offset + 3],
+            ];
+            f32::from_le_bytes(bytes)
+        };
+        // Skip the facet normal, then read the three vertices.
+        for vertex in 1..4 {
+            let base = 12 * vertex;
+            positions.push(Vector3::new(
+                read_f32(base),
+                read_f32(base + 4),
+                read_f32(base + 8),
+            ));
+        }
+    }
+    positions
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<Vector3<f32>>, String> {
+    let text = std::str::from_utf8(data).map_err(|_| "not a valid STL file")?;
+    let mut tokens = text.split_whitespace();
+    if tokens.next() != Some("solid") {
+        return Err("not a valid STL file".to_string());
+    }
+
+    let mut positions = Vec::new();
+    let mut vertex_count = 0;
+    while let Some(token) = tokens.next() {
+        match token {
+            "facet" => vertex_count = 0,
+            "vertex" => {
+                let mut coordinate = || -> Result<f32, String> {
+                    let token = tokens.next().ok_or("unexpected end of file")?;
+                    token
+                        .parse()
+                        .map_err(|_| format!("invalid number '{token}'"))
+                };
+                positions.push(Vector3::new(coordinate()?, coordinate()?, coordinate()?));
+                vertex_count += 1;
+            }
+            "endfacet" if vertex_count != 3 => {
+                return Err(format!("facet needs 3 vertices, got {vertex_count}"));
+            }
+            _ => {}
+        }
+    }
+    Ok(positions)
+}
@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use image::{DynamicImage, ImageResult};
+use nalgebra::Vector3;
+
+pub trait Texture: Send + Sync {
+    /// Returns the texture color at the surface coordinates `u`, `v` of the point `p`.
+    fn value(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32>;
+}
+
+pub struct SolidColor {
+    albedo: Vector3<f32>,
+}
+
+impl SolidColor {
+    pub fn new(albedo: Vector3<f32>) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f32, _v: f32, _p: &Vector3<f32>) -> Vector3<f32> {
+        self.albedo
+    }
+}
+
+/// A texture sampled from an image file, repeating outside of the [0, 1] coordinate range.
+#[derive(Clone)]
+pub struct ImageTexture {
+    width: u32,
+    height: u32,
+    /// Pixel colors in linear space, stored row by row from the top of the image.
+    pixels: Vec<Vector3<f32>>,
+    /// Pixel opacities, for images with an alpha channel.
+    alphas: Option<Vec<f32>>,
+}
+
+impl ImageTexture {
+    /// Load an image from disk. 8-bit images are assumed to be sRGB encoded and are converted to
+    /// linear space, floating point images (e.g. HDR) are used as is.
+    pub fn open(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Self::from_image(image::open(path)?, true))
+    }
+
+    /// Load an image holding non-color data, such as roughness or metalness, whose values are
+    /// used as is whatever their bit depth.
+    pub fn open_linear(path: impl AsRef<Path>) -> ImageResult<Self> {
+        Ok(Self::from_image(image::open(path)?, false))
+    }
+
+    /// Decode an image held in memory, like [`ImageTexture::open`] does for files.
+    pub fn from_memory(bytes: &[u8]) -> ImageResult<Self> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?, true))
+    }
+
+    /// Decode an image held in memory, like [`ImageTexture::open_linear`] does for files.
+    pub fn from_memory_linear(bytes: &[u8]) -> ImageResult<Self> {
+        Ok(Self::from_image(image::load_from_memory(bytes)?, false))
+    }
+
+    fn from_image(image: DynamicImage, srgb: bool) -> Self {
+        let is_float = matches!(
+            image.color(),
+            image::ColorType::Rgb32F | image::ColorType::Rgba32F
+        );
+        let has_alpha = image.color().has_alpha();
+        let image = image.into_rgba32f();
+
+        let pixels = image
+            .pixels()
+            .map(|p| {
+                let color = Vector3::new(p[0], p[1], p[2]);
+                if is_float || !srgb {
+                    color
+                } else {
+                    color.map(|c| c.powf(2.2))
+                }
+            })
+            .collect();
+        let alphas = has_alpha.then(|| image.pixels().map(|p| p[3]).collect());
+
+        Self {
+            width: image.width(),
+            height: image.height(),
+            pixels,
+            alphas,
+        }
+    }
+
+    pub fn has_alpha(&self) -> bool {
+        self.alphas.is_some()
+    }
+
+    /// A texture of the opacity of this image, in all three channels, for use as an alpha mask.
+    /// Images without an alpha channel are fully opaque.
+    pub fn alpha_channel(&self) -> Self {
+        let pixels = match &self.alphas {
+            Some(alphas) => alphas.iter().map(|&a| Vector3::repeat(a)).collect(),
+            None => vec![Vector3::new(1.0, 1.0, 1.0); self.pixels.len()],
+        };
+        Self {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alphas: None,
+        }
+    }
+
+    /// Multiply every pixel of the texture by `factor`.
+    pub fn tinted(mut self, factor: Vector3<f32>) -> Self {
+        for pixel in self.pixels.iter_mut() {
+            *pixel = pixel.component_mul(&factor);
+        }
+        self
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Vector3<f32> {
+        self.pixels[(y * self.width + x) as usize]
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f32, v: f32, _p: &Vector3<f32>) -> Vector3<f32> {
+        if self.pixels.is_empty() {
+            // Return solid cyan as a debugging aid when there is no texture data.
+            return Vector3::new(0.0, 1.0, 1.0);
+        }
+
+        // Wrap the texture coordinates and flip V to image coordinates.
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+
+        let i = ((u * self.width as f32) as u32).min(self.width - 1);
+        let j = ((v * self.height as f32) as u32).min(self.height - 1);
+        self.pixel(i, j)
+    }
+}
+
+/// A single channel of another texture, repeated in all three channels. This is how scalar
+/// parameters packed into the channels of one image are read.
+pub struct ChannelTexture {
+    tex: Arc<dyn Texture>,
+    channel: usize,
+}
+
+impl ChannelTexture {
+    /// Panics if `channel` is not 0, 1 or 2.
+    pub fn new(tex: Arc<dyn Texture>, channel: usize) -> Self {
+        assert!(channel < 3, "texture channel out of range");
+        Self { tex, channel }
+    }
+}
+
+impl Texture for ChannelTexture {
+    fn value(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32> {
+        Vector3::repeat(self.tex.value(u, v, p)[self.channel])
+    }
+}
+
+/// Another texture multiplied by a constant color, which lets one image be shared by materials
+/// applying different factors to it.
+pub struct TintedTexture {
+    tex: Arc<dyn Texture>,
+    factor: Vector3<f32>,
+}
+
+impl TintedTexture {
+    pub fn new(tex: Arc<dyn Texture>, factor: Vector3<f32>) -> Self {
+        Self { tex, factor }
+    }
+}
+
+impl Texture for TintedTexture {
+    fn value(&self, u: f32, v: f32, p: &Vector3<f32>) -> Vector3<f32> {
+        self.tex.value(u, v, p).component_mul(&self.factor)
+    }
+}
+
+/// Conversion into a texture, so that material parameters can be given either as constants or as
+/// textures.
+pub trait IntoTexture {
+    fn into_texture(self) -> Arc<dyn Texture>;
+}
+
+impl IntoTexture for f32 {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        Arc::new(SolidColor::new(Vector3::repeat(self)))
+    }
+}
+
+impl IntoTexture for Vector3<f32> {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        Arc::new(SolidColor::new(self))
+    }
+}
+
+impl IntoTexture for Arc<dyn Texture> {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        self
+    }
+}
+
+impl<T: Texture + 'static> IntoTexture for Arc<T> {
+    fn into_texture(self) -> Arc<dyn Texture> {
+        self
+    }
+}
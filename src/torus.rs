@@ -0,0 +1,236 @@
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+use nalgebra::Vector3;
+
+use crate::aabb::Aabb;
+use crate::cylinder::{angle_u, LocalHit};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::onb::Onb;
+use crate::ray::Ray;
+
+/// A ring shaped tube of radius `minor_radius`, swept around `axis` at a distance of
+/// `major_radius` from the center.
+pub struct Torus {
+    center: Vector3<f32>,
+    major_radius: f32,
+    minor_radius: f32,
+    onb: Onb,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Torus {
+    pub fn new(
+        center: Vector3<f32>,
+        axis: Vector3<f32>,
+        major_radius: f32,
+        minor_radius: f32,
+        mat: Arc<dyn Material>,
+    ) -> Self {
+        let onb = Onb::new(&axis);
+        // The extent of the ring in its own plane, plus the tube thickness along the axis.
+        let extent = onb.w().map(|n| {
+            (major_radius + minor_radius) * (1.0 - n * n).max(0.0).sqrt() + minor_radius * n.abs()
+        });
+        Self {
+            center,
+            major_radius,
+            minor_radius,
+            onb,
+            mat,
+            bbox: Aabb::from_points(center - extent, center + extent),
+        }
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if !self.bbox.hit(r, ray_t) {
+            return None;
+        }
+
+        // Solve in the torus's local frame, centered at the origin with its axis along z. The
+        // quartic is set up in double precision with a unit length direction, starting from a
+        // point close to the torus to keep the coefficients well conditioned.
+        let scale = r.direction().magnitude() as f64;
+        let d = self.onb.to_basis(&r.direction()).cast::<f64>() / scale;
+        let mut o = self.onb.to_basis(&(r.origin() - self.center)).cast::<f64>();
+        let big_r = self.major_radius as f64;
+        let small_r = self.minor_radius as f64;
+        let t_shift = (-o.dot(&d) - (big_r + small_r)).max(0.0);
+        o += t_shift * d;
+
+        // (|p|² - R² - r²)² - 4R² (r² - z²) = 0, for p = o + t d.
+        let f = o.dot(&d);
+        let e = o.magnitude_squared() - big_r * big_r - small_r * small_r;
+        let four_r2 = 4.0 * big_r * big_r;
+        let coefficients = [
+            e * e - four_r2 * (small_r * small_r - o.z * o.z),
+            4.0 * f * e + 2.0 * four_r2 * o.z * d.z,
+            2.0 * e + 4.0 * f * f + four_r2 * d.z * d.z,
+            4.0 * f,
+        ];
+
+        let (roots, count) = solve_quartic(coefficients);
+        let t = roots[..count]
+            .iter()
+            .map(|&t| polish_root(coefficients, t))
+            .map(|t| ((t + t_shift) / scale) as f32)
+            .filter(|&t| ray_t.surrounds(t))
+            .min_by(f32::total_cmp)?;
+
+        let p = self.onb.to_basis(&(r.at(t) - self.center));
+        let ring_distance = (p.x * p.x + p.y * p.y).sqrt();
+        // The tube center closest to the hit point lies on the ring in the local xy plane.
+        let tube_center = if ring_distance > 0.0 {
+            Vector3::new(p.x, p.y, 0.0) * (self.major_radius / ring_distance)
+        } else {
+            Vector3::new(self.major_radius, 0.0, 0.0)
+        };
+
+        let hit = LocalHit {
+            t,
+            normal: (p - tube_center) / self.minor_radius,
+            u: angle_u(&p),
+            v: (p.z.atan2(ring_distance - self.major_radius) + PI) / (2.0 * PI),
+        };
+        Some(hit.into_record(r, &self.onb, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Refine a root of the monic quartic with a few Newton iterations.
+fn polish_root(c: [f64; 4], mut t: f64) -> f64 {
+    for _ in 0..2 {
+        let value = (((t + c[3]) * t + c[2]) * t + c[1]) * t + c[0];
+        let derivative = ((4.0 * t + 3.0 * c[3]) * t + 2.0 * c[2]) * t + c[1];
+        if derivative == 0.0 {
+            break;
+        }
+        t -= value / derivative;
+    }
+    t
+}
+
+/// Real roots of `x² + c[1] x + c[0]`, in the first elements of the array followed by their
+/// number.
+fn solve_quadric(c: [f64; 2]) -> ([f64; 2], usize) {
+    let p = c[1] / 2.0;
+    let q = c[0];
+    let discriminant = p * p - q;
+    if discriminant.abs() < 1e-12 {
+        ([-p, 0.0], 1)
+    } else if discriminant < 0.0 {
+        ([0.0; 2], 0)
+    } else {
+        let sqrt_d = discriminant.sqrt();
+        ([sqrt_d - p, -sqrt_d - p], 2)
+    }
+}
+
+/// Real roots of `x³ + c[2] x² + c[1] x + c[0]`, using Cardano's formula. There is always at
+/// least one.
+fn solve_cubic(c: [f64; 3]) -> ([f64; 3], usize) {
+    // Substitute x = y - A/3 to eliminate the quadric term: y³ + 3p y + 2q = 0.
+    let a = c[2];
+    let sq_a = a * a;
+    let p = (-sq_a / 3.0 + c[1]) / 3.0;
+    let q = (2.0 / 27.0 * a * sq_a - a * c[1] / 3.0 + c[0]) / 2.0;
+
+    let cb_p = p * p * p;
+    let discriminant = q * q + cb_p;
+
+    let (mut roots, count) = if discriminant.abs() < 1e-12 {
+        if q.abs() < 1e-12 {
+            // One triple solution.
+            ([0.0; 3], 1)
+        } else {
+            // One single and one double solution.
+            let u = (-q).cbrt();
+            ([2.0 * u, -u, 0.0], 2)
+        }
+    } else if discriminant < 0.0 {
+        // Three real solutions.
+        let phi = (-q / (-cb_p).sqrt()).clamp(-1.0, 1.0).acos() / 3.0;
+        let t = 2.0 * (-p).sqrt();
+        (
+            [
+                t * phi.cos(),
+                -t * (phi + std::f64::consts::PI / 3.0).cos(),
+                -t * (phi - std::f64::consts::PI / 3.0).cos(),
+            ],
+            3,
+        )
+    } else {
+        // One real solution.
+        let sqrt_d = discriminant.sqrt();
+        ([(sqrt_d - q).cbrt() - (sqrt_d + q).cbrt(), 0.0, 0.0], 1)
+    };
+
+    for root in roots[..count].iter_mut() {
+        *root -= a / 3.0;
+    }
+    (roots, count)
+}
+
+/// Real roots of `x⁴ + c[3] x³ + c[2] x² + c[1] x + c[0]`, using Ferrari's method, in the first
+/// elements of the array followed by their number.
+fn solve_quartic(c: [f64; 4]) -> ([f64; 4], usize) {
+    // Substitute x = y - A/4 to eliminate the cubic term: y⁴ + p y² + q y + r = 0.
+    let a = c[3];
+    let sq_a = a * a;
+    let p = -3.0 / 8.0 * sq_a + c[2];
+    let q = 1.0 / 8.0 * sq_a * a - 1.0 / 2.0 * a * c[2] + c[1];
+    let r = -3.0 / 256.0 * sq_a * sq_a + 1.0 / 16.0 * sq_a * c[2] - 1.0 / 4.0 * a * c[1] + c[0];
+
+    let mut roots = [0.0; 4];
+    let mut count = 0;
+    let mut push = |found: &[f64]| {
+        roots[count..count + found.len()].copy_from_slice(found);
+        count += found.len();
+    };
+    if r.abs() < 1e-12 {
+        // No absolute term: y (y³ + p y + q) = 0.
+        let (cubic, cubic_count) = solve_cubic([q, p, 0.0]);
+        push(&cubic[..cubic_count]);
+        push(&[0.0]);
+    } else {
+        // Solve the resolvent cubic, and use one of its roots to split the quartic into two
+        // quadrics.
+        let z = solve_cubic([1.0 / 2.0 * r * p - 1.0 / 8.0 * q * q, -r, -1.0 / 2.0 * p]).0[0];
+
+        let u = z * z - r;
+        let v = 2.0 * z - p;
+        let u = if u.abs() < 1e-12 {
+            0.0
+        } else if u > 0.0 {
+            u.sqrt()
+        } else {
+            return ([0.0; 4], 0);
+        };
+        let v = if v.abs() < 1e-12 {
+            0.0
+        } else if v > 0.0 {
+            v.sqrt()
+        } else {
+            return ([0.0; 4], 0);
+        };
+
+        let v_q = if q < 0.0 { -v } else { v };
+        let (first, first_count) = solve_quadric([z - u, v_q]);
+        push(&first[..first_count]);
+        let (second, second_count) = solve_quadric([z + u, -v_q]);
+        push(&second[..second_count]);
+    }
+
+    for root in roots[..count].iter_mut() {
+        *root -= a / 4.0;
+    }
+    (roots, count)
+}
@@ -0,0 +1,327 @@
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use nalgebra::{Matrix3, Matrix4, UnitQuaternion, Vector3};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::ray::Ray;
+
+/// An instance of a shared object, placed in the world by an affine transformation.
+///
+/// Rays are moved into the object's space for intersection, and the resulting hit is moved back
+/// out, so the same object (typically a mesh with its own BVH) can be placed many times without
+/// copying any of its geometry.
+pub struct Transform {
+    object: Arc<dyn Hittable>,
+    motion: Motion,
+    bbox: Aabb,
+}
+
+enum Motion {
+    Static(Placement),
+    /// Keyframes sorted by time, interpolated for each ray.
+    Keyframed(Vec<Keyframe>),
+}
+
+/// An object to world transformation, together with the matrices derived from it.
+#[derive(Clone)]
+struct Placement {
+    /// Object to world space.
+    matrix: Matrix4<f32>,
+    /// World to object space.
+    inverse: Matrix4<f32>,
+    /// Transforms object space normals to world space: the inverse transpose of the linear part
+    /// of `matrix`, which keeps normals perpendicular to surfaces under non-uniform scaling.
+    normal_matrix: Matrix3<f32>,
+}
+
+impl Placement {
+    /// Panics if the matrix is not invertible.
+    fn new(matrix: Matrix4<f32>) -> Self {
+        let inverse = matrix
+            .try_inverse()
+            .expect("transform matrix must be invertible");
+        let normal_matrix = inverse.fixed_view::<3, 3>(0, 0).transpose();
+        Self {
+            matrix,
+            inverse,
+            normal_matrix,
+        }
+    }
+
+    /// The placement of a keyframe, built from its parts without a general matrix inversion, or
+    /// `None` if its scale is zero along some axis.
+    fn from_keyframe(keyframe: &Keyframe) -> Option<Self> {
+        if keyframe.scale.iter().any(|&s| s == 0.0) {
+            return None;
+        }
+        let inverse_scale = keyframe.scale.map(|s| 1.0 / s);
+        let inverse = Matrix4::new_nonuniform_scaling(&inverse_scale)
+            * keyframe.rotation.inverse().to_homogeneous()
+            * Matrix4::new_translation(&-keyframe.translation);
+        // The inverse transpose of the linear part, rotation times scale.
+        let normal_matrix = keyframe.rotation.to_rotation_matrix().into_inner()
+            * Matrix3::from_diagonal(&inverse_scale);
+        Some(Self {
+            matrix: keyframe.matrix(),
+            inverse,
+            normal_matrix,
+        })
+    }
+
+    /// Moves a world space ray into object space. The direction is not renormalized, so ray
+    /// parameters are the same in both spaces.
+    fn object_ray(&self, r: &Ray) -> Ray {
+        let origin = self.inverse.transform_point(&r.origin().into()).coords;
+        let direction = self.inverse.transform_vector(&r.direction());
+        Ray::new(origin, direction, r.time())
+    }
+}
+
+/// The placement of a moving object at a given time. Between keyframes, translation and scale
+/// are interpolated linearly and rotation spherically.
+#[derive(Clone, Copy, Debug)]
+pub struct Keyframe {
+    pub time: f32,
+    pub translation: Vector3<f32>,
+    pub rotation: UnitQuaternion<f32>,
+    pub scale: Vector3<f32>,
+}
+
+impl Keyframe {
+    pub fn new(
+        time: f32,
+        translation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        scale: Vector3<f32>,
+    ) -> Self {
+        Self {
+            time,
+            translation,
+            rotation,
+            scale,
+        }
+    }
+
+    fn matrix(&self) -> Matrix4<f32> {
+        compose(&self.translation, &self.rotation, &self.scale)
+    }
+
+    /// Blends towards `other`, with `s` running from 0 (`self`) to 1 (`other`).
+    fn interpolate(&self, other: &Keyframe, s: f32) -> Keyframe {
+        Keyframe {
+            time: self.time + s * (other.time - self.time),
+            translation: self.translation.lerp(&other.translation, s),
+            rotation: self.rotation.slerp(&other.rotation, s),
+            scale: self.scale.lerp(&other.scale, s),
+        }
+    }
+}
+
+impl Transform {
+    /// Places `object` in the world with the affine object-to-world matrix `matrix`.
+    ///
+    /// Panics if the matrix is not invertible.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Matrix4<f32>) -> Self {
+        let placement = Placement::new(matrix);
+        let bbox = transform_bbox(&object.bounding_box(), &matrix);
+        Self {
+            object,
+            motion: Motion::Static(placement),
+            bbox,
+        }
+    }
+
+    pub fn translation(object: Arc<dyn Hittable>, offset: Vector3<f32>) -> Self {
+        Self::new(object, Matrix4::new_translation(&offset))
+    }
+
+    /// Rotates `object` around the world origin.
+    pub fn rotation(object: Arc<dyn Hittable>, rotation: UnitQuaternion<f32>) -> Self {
+        Self::new(object, rotation.to_homogeneous())
+    }
+
+    /// Scales `object` by a possibly different factor along each axis, around the world origin.
+    pub fn scaling(object: Arc<dyn Hittable>, scale: Vector3<f32>) -> Self {
+        Self::new(object, Matrix4::new_nonuniform_scaling(&scale))
+    }
+
+    /// Composes translation, rotation and scale, applied to the object in the order scale,
+    /// rotation, translation.
+    pub fn from_parts(
+        object: Arc<dyn Hittable>,
+        translation: Vector3<f32>,
+        rotation: UnitQuaternion<f32>,
+        scale: Vector3<f32>,
+    ) -> Self {
+        Self::new(object, compose(&translation, &rotation, &scale))
+    }
+
+    /// Moves `object` through the given keyframes. Rays cast before the first or after the last
+    /// keyframe see the object held at that keyframe. The bounding box encloses the whole motion.
+    /// Rays cast at a time where the interpolated placement flattens the object, such as a scale
+    /// passing through zero, miss it.
+    ///
+    /// Panics if there are no keyframes, or if any of them is not invertible.
+    pub fn keyframed(object: Arc<dyn Hittable>, mut keyframes: Vec<Keyframe>) -> Self {
+        assert!(!keyframes.is_empty(), "keyframed transform needs keyframes");
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        // Fail early rather than on the first ray at a degenerate keyframe.
+        for keyframe in &keyframes {
+            assert!(
+                Placement::from_keyframe(keyframe).is_some(),
+                "transform matrix must be invertible"
+            );
+        }
+
+        let object_bbox = object.bounding_box();
+        let mut bbox = transform_bbox(&object_bbox, &keyframes[0].matrix());
+        for pair in keyframes.windows(2) {
+            bbox = Aabb::enclosing(&bbox, &motion_bbox(&object_bbox, &pair[0], &pair[1]));
+        }
+
+        Self {
+            object,
+            motion: Motion::Keyframed(keyframes),
+            bbox,
+        }
+    }
+
+    /// The placement of the object at `time`, or `None` where its motion flattens it.
+    fn placement_at(&self, time: f32) -> Option<Cow<'_, Placement>> {
+        match &self.motion {
+            Motion::Static(placement) => Some(Cow::Borrowed(placement)),
+            Motion::Keyframed(keyframes) => {
+                Placement::from_keyframe(&keyframe_at(keyframes, time)).map(Cow::Owned)
+            }
+        }
+    }
+}
+
+impl Hittable for Transform {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let placement = self.placement_at(r.time())?;
+
+        // Ray parameters are the same in both spaces, so `ray_t` and `rec.t` need no conversion.
+        let mut rec = self.object.hit(&placement.object_ray(r), ray_t)?;
+
+        rec.p = placement.matrix.transform_point(&rec.p.into()).coords;
+        // The dot product between the ray direction and the normal keeps its sign under the
+        // transformation, so `front_face` remains valid.
+        rec.normal = (placement.normal_matrix * rec.normal).normalize();
+        rec.tangent = placement.matrix.transform_vector(&rec.tangent);
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f32 {
+        match self.placement_at(r.time()) {
+            Some(placement) => self.object.transmittance(&placement.object_ray(r), ray_t),
+            None => 1.0,
+        }
+    }
+}
+
+/// Object to world matrix applying scale, rotation and translation, in that order.
+fn compose(
+    translation: &Vector3<f32>,
+    rotation: &UnitQuaternion<f32>,
+    scale: &Vector3<f32>,
+) -> Matrix4<f32> {
+    Matrix4::new_translation(translation)
+        * rotation.to_homogeneous()
+        * Matrix4::new_nonuniform_scaling(scale)
+}
+
+/// The interpolated keyframe at `time`, held constant outside the keyframed range.
+fn keyframe_at(keyframes: &[Keyframe], time: f32) -> Keyframe {
+    let next = keyframes.partition_point(|keyframe| keyframe.time <= time);
+    if next == 0 {
+        return keyframes[0];
+    }
+    if next == keyframes.len() {
+        return keyframes[next - 1];
+    }
+    let (a, b) = (&keyframes[next - 1], &keyframes[next]);
+    a.interpolate(b, (time - a.time) / (b.time - a.time))
+}
+
+/// The box enclosing `bbox` while it moves from keyframe `a` to keyframe `b`.
+///
+/// The motion is sampled at regular steps. Since rotating points sweep arcs that bulge out of the
+/// boxes at the sampled steps, each step is padded by the largest distance between such an arc
+/// and its chord.
+fn motion_bbox(bbox: &Aabb, a: &Keyframe, b: &Keyframe) -> Aabb {
+    const STEPS: usize = 16;
+    if is_empty(bbox) {
+        return Aabb::EMPTY;
+    }
+
+    // The furthest any point of the scaled box can be from the center of rotation.
+    let max_scale = a.scale.abs().sup(&b.scale.abs());
+    let radius = (0..3)
+        .map(|axis| {
+            let interval = bbox.axis_interval(axis);
+            let extent = interval.min.abs().max(interval.max.abs()) * max_scale[axis];
+            extent * extent
+        })
+        .sum::<f32>()
+        .sqrt();
+    let step_angle = a.rotation.angle_to(&b.rotation) / STEPS as f32;
+    // Unbounded objects already have unbounded boxes wherever they sweep.
+    let bulge = if step_angle > 0.0 && radius.is_finite() {
+        radius * (1.0 - (step_angle / 2.0).cos())
+    } else {
+        0.0
+    };
+
+    let mut result = Aabb::EMPTY;
+    for step in 0..=STEPS {
+        let keyframe = a.interpolate(b, step as f32 / STEPS as f32);
+        let step_bbox = transform_bbox(bbox, &keyframe.matrix());
+        result = Aabb::enclosing(
+            &result,
+            &Aabb::new(
+                step_bbox.x.expand(2.0 * bulge),
+                step_bbox.y.expand(2.0 * bulge),
+                step_bbox.z.expand(2.0 * bulge),
+            ),
+        );
+    }
+    result
+}
+
+/// The axis-aligned box enclosing `bbox` after an affine transformation.
+///
+/// Each world axis extent is accumulated from the contributions of the object axes, which is
+/// exact for the transformed corners and keeps unbounded boxes well defined where the matrix has
+/// zero entries.
+fn transform_bbox(bbox: &Aabb, matrix: &Matrix4<f32>) -> Aabb {
+    if is_empty(bbox) {
+        return Aabb::EMPTY;
+    }
+    let mut min = Vector3::new(matrix[(0, 3)], matrix[(1, 3)], matrix[(2, 3)]);
+    let mut max = min;
+    for i in 0..3 {
+        for j in 0..3 {
+            let m = matrix[(i, j)];
+            if m == 0.0 {
+                continue;
+            }
+            let axis = bbox.axis_interval(j);
+            let (a, b) = (m * axis.min, m * axis.max);
+            min[i] += a.min(b);
+            max[i] += a.max(b);
+        }
+    }
+    Aabb::from_points(min, max)
+}
+
+fn is_empty(bbox: &Aabb) -> bool {
+    (0..3).any(|axis| bbox.axis_interval(axis).size() < 0.0)
+}
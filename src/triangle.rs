@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use nalgebra::{Vector2, Vector3};
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+
+pub struct Triangle {
+    vertices: [Vector3<f32>; 3],
+    attributes: VertexAttributes,
+    mat: Arc<dyn Material>,
+    bbox: Aabb,
+}
+
+impl Triangle {
+    /// Creates a flat shaded triangle from three vertices in counter-clockwise order.
+    pub fn new(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>, mat: Arc<dyn Material>) -> Self {
+        Self {
+            vertices: [a, b, c],
+            attributes: VertexAttributes {
+                normals: None,
+                uvs: DEFAULT_UVS,
+                colors: None,
+            },
+            mat,
+            bbox: Aabb::enclosing(&Aabb::from_points(a, b), &Aabb::from_points(a, c)),
+        }
+    }
+
+    /// Sets per-vertex normals, which are interpolated across the face for smooth shading.
+    pub fn with_normals(mut self, normals: [Vector3<f32>; 3]) -> Self {
+        self.attributes.normals = Some(normals.map(|n| n.normalize()));
+        self
+    }
+
+    /// Sets per-vertex texture coordinates.
+    pub fn with_uvs(mut self, uvs: [Vector2<f32>; 3]) -> Self {
+        self.attributes.uvs = uvs;
+        self
+    }
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (t, b1, b2) = intersect(r, ray_t, &self.vertices)?;
+        Some(hit_record(
+            r,
+            t,
+            (b1, b2),
+            &self.vertices,
+            &self.attributes,
+            Arc::clone(&self.mat),
+        ))
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Per-vertex shading attributes of a triangle.
+pub(crate) struct VertexAttributes {
+    pub normals: Option<[Vector3<f32>; 3]>,
+    pub uvs: [Vector2<f32>; 3],
+    pub colors: Option<[Vector3<f32>; 3]>,
+}
+
+/// Texture coordinates used when a triangle has none of its own.
+pub(crate) const DEFAULT_UVS: [Vector2<f32>; 3] = [
+    Vector2::new(0.0, 0.0),
+    Vector2::new(1.0, 0.0),
+    Vector2::new(0.0, 1.0),
+];
+
+/// Möller–Trumbore ray/triangle intersection. Returns the ray parameter `t` and the barycentric
+/// coordinates of the hit point with respect to the second and third vertex.
+pub(crate) fn intersect(
+    r: &Ray,
+    ray_t: Interval,
+    vertices: &[Vector3<f32>; 3],
+) -> Option<(f32, f32, f32)> {
+    let edge1 = vertices[1] - vertices[0];
+    let edge2 = vertices[2] - vertices[0];
+
+    let pvec = r.direction().cross(&edge2);
+    let det = edge1.dot(&pvec);
+    // The ray is parallel to the plane of the triangle. The determinant scales with the lengths
+    // of the direction and the edges, so it is compared relative to them.
+    let scale = (r.direction().norm_squared() * edge1.norm_squared() * edge2.norm_squared()).sqrt();
+    if det.abs() <= 1e-8 * scale {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = r.origin() - vertices[0];
+    let b1 = tvec.dot(&pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = tvec.cross(&edge1);
+    let b2 = r.direction().dot(&qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(&qvec) * inv_det;
+    if !ray_t.surrounds(t) {
+        return None;
+    }
+    Some((t, b1, b2))
+}
+
+/// Builds the hit record for an intersection found by [`intersect`], interpolating the vertex
+/// attributes at the barycentric coordinates `(b1, b2)`.
+pub(crate) fn hit_record(
+    r: &Ray,
+    t: f32,
+    (b1, b2): (f32, f32),
+    vertices: &[Vector3<f32>; 3],
+    attributes: &VertexAttributes,
+    mat: Arc<dyn Material>,
+) -> HitRecord {
+    let b0 = 1.0 - b1 - b2;
+
+    let mut rec = HitRecord::new(r.at(t), t, mat);
+    let geometric_normal = (vertices[1] - vertices[0])
+        .cross(&(vertices[2] - vertices[0]))
+        .normalize();
+    rec.set_face_normal(r, geometric_normal);
+
+    // The face orientation is decided by the geometric normal, the interpolated normal only
+    // affects shading. Where the vertex normals cancel out, the geometric normal is kept.
+    if let Some(shading_normal) = attributes
+        .normals
+        .and_then(|n| (b0 * n[0] + b1 * n[1] + b2 * n[2]).try_normalize(0.0))
+    {
+        rec.normal = if rec.front_face {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+    }
+
+    let uvs = attributes.uvs;
+    let uv = b0 * uvs[0] + b1 * uvs[1] + b2 * uvs[2];
+    rec.u = uv.x;
+    rec.v = uv.y;
+
+    // Solve for the direction of increasing u from the texture coordinates at the vertices,
+    // following the first edge where they are degenerate.
+    let (edge1, edge2) = (vertices[1] - vertices[0], vertices[2] - vertices[0]);
+    let (duv1, duv2) = (uvs[1] - uvs[0], uvs[2] - uvs[0]);
+    let det = duv1.x * duv2.y - duv2.x * duv1.y;
+    rec.tangent = if det.abs() > 1e-12 {
+        (edge1 * duv2.y - edge2 * duv1.y) / det
+    } else {
+        edge1
+    };
+
+    if let Some(c) = attributes.colors {
+        rec.color = b0 * c[0] + b1 * c[1] + b2 * c[2];
+    }
+    rec
+}
@@ -0,0 +1,182 @@
+//! Dense voxel grids of scalar values, such as the density or temperature of a heterogeneous
+//! volume.
+//!
+//! Grids can be generated procedurally or loaded from raw files: a headerless sequence of
+//! little-endian 32-bit floats, with x varying fastest, then y, then z. The resolution is not
+//! stored in the file and has to be given when loading.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use nalgebra::Vector3;
+
+use crate::perlin::Perlin;
+
+#[derive(Debug)]
+pub enum VoxelError {
+    /// The file could not be read.
+    Io { path: PathBuf, source: io::Error },
+    /// The file size does not match the requested resolution.
+    Size {
+        path: PathBuf,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl fmt::Display for VoxelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VoxelError::Io { path, source } => {
+                write!(f, "failed to read {}: {source}", path.display())
+            }
+            VoxelError::Size {
+                path,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: expected {expected} bytes for the grid resolution, found {actual}",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for VoxelError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            VoxelError::Io { source, .. } => Some(source),
+            VoxelError::Size { .. } => None,
+        }
+    }
+}
+
+/// A grid of values sampled at the voxel centers and interpolated trilinearly in between.
+pub struct VoxelGrid {
+    resolution: [usize; 3],
+    values: Vec<f32>,
+    max_value: f32,
+}
+
+impl VoxelGrid {
+    /// Creates a grid from its values, with x varying fastest, then y, then z.
+    ///
+    /// Panics if the grid is empty or the number of values doesn't match the resolution.
+    pub fn new(resolution: [usize; 3], values: Vec<f32>) -> Self {
+        assert!(
+            resolution.iter().all(|&n| n > 0),
+            "voxel grid must not be empty"
+        );
+        assert_eq!(
+            values.len(),
+            resolution.iter().product::<usize>(),
+            "voxel count must match the grid resolution"
+        );
+        let max_value = values.iter().copied().fold(0.0, f32::max);
+        Self {
+            resolution,
+            values,
+            max_value,
+        }
+    }
+
+    /// Fills the grid by evaluating `f` at each voxel center, given in [0, 1] grid coordinates.
+    pub fn from_fn(resolution: [usize; 3], mut f: impl FnMut(Vector3<f32>) -> f32) -> Self {
+        let [nx, ny, nz] = resolution;
+        let mut values = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    values.push(f(Vector3::new(
+                        (x as f32 + 0.5) / nx as f32,
+                        (y as f32 + 0.5) / ny as f32,
+                        (z as f32 + 0.5) / nz as f32,
+                    )));
+                }
+            }
+        }
+        Self::new(resolution, values)
+    }
+
+    /// Generates a billowing, cloud-like grid from Perlin turbulence with `octaves` octaves,
+    /// thinning out towards the boundary of the grid so the volume has no hard edges. `frequency`
+    /// is the number of noise features across the grid.
+    pub fn from_noise(resolution: [usize; 3], frequency: f32, octaves: u32) -> Self {
+        let perlin = Perlin::new();
+        Self::from_fn(resolution, |p| {
+            // The noise has to exceed a threshold rising towards the boundary, eroding the edges.
+            let threshold = 2.0 * (p - Vector3::new(0.5, 0.5, 0.5)).magnitude();
+            (2.0 * perlin.turb(&(frequency * p), octaves) + 0.5 - threshold).max(0.0)
+        })
+    }
+
+    /// Loads a raw grid file with the given resolution.
+    pub fn load_raw(path: impl AsRef<Path>, resolution: [usize; 3]) -> Result<Self, VoxelError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|source| VoxelError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let expected = 4 * resolution.iter().product::<usize>();
+        if data.len() != expected {
+            return Err(VoxelError::Size {
+                path: path.to_path_buf(),
+                expected,
+                actual: data.len(),
+            });
+        }
+
+        let values = data
+            .chunks_exact(4)
+            .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+            .collect();
+        Ok(Self::new(resolution, values))
+    }
+
+    pub fn resolution(&self) -> [usize; 3] {
+        self.resolution
+    }
+
+    /// The largest value in the grid, bounding every interpolated sample.
+    pub fn max_value(&self) -> f32 {
+        self.max_value
+    }
+
+    /// Interpolates the grid at `p`, given in [0, 1] grid coordinates. Points outside the grid
+    /// take the value of the nearest boundary voxels.
+    pub fn sample(&self, p: &Vector3<f32>) -> f32 {
+        // Continuous voxel coordinates, with voxel centers at integers.
+        let mut base = [0; 3];
+        let mut frac = [0.0; 3];
+        for axis in 0..3 {
+            let n = self.resolution[axis];
+            let x = (p[axis] * n as f32 - 0.5).clamp(0.0, (n - 1) as f32);
+            base[axis] = (x.floor() as usize).min(n.saturating_sub(2));
+            frac[axis] = x - base[axis] as f32;
+        }
+
+        let mut accum = 0.0;
+        for dz in 0..2 {
+            for dy in 0..2 {
+                for dx in 0..2 {
+                    let weight = (if dx == 1 { frac[0] } else { 1.0 - frac[0] })
+                        * (if dy == 1 { frac[1] } else { 1.0 - frac[1] })
+                        * (if dz == 1 { frac[2] } else { 1.0 - frac[2] });
+                    if weight > 0.0 {
+                        accum += weight * self.value(base[0] + dx, base[1] + dy, base[2] + dz);
+                    }
+                }
+            }
+        }
+        accum
+    }
+
+    fn value(&self, x: usize, y: usize, z: usize) -> f32 {
+        let [nx, ny, _] = self.resolution;
+        self.values[(z * ny + y) * nx + x]
+    }
+}